@@ -5,21 +5,25 @@ use std::env;
 use std::ffi::{CStr, CString};
 use std::hash::{Hash, Hasher};
 use std::os::raw::{c_char, c_void};
+use std::str::FromStr;
 
 use log::{info, LevelFilter};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use rgb::lnpbp::bitcoin::OutPoint;
+use rgb::lnpbp::bitcoin::consensus::deserialize;
+use rgb::lnpbp::bitcoin::hashes::hex::FromHex;
+use rgb::lnpbp::bitcoin::secp256k1;
+use rgb::lnpbp::bitcoin::{OutPoint, Transaction};
 
 use rgb::lnpbp::bp;
 use rgb::lnpbp::lnp::transport::zmq::{SocketLocator, UrlError};
-use rgb::lnpbp::rgb::{seal, Amount};
+use rgb::lnpbp::rgb::{seal, Amount, ContractId};
 
-use rgb::fungible::{Invoice, IssueStructure, Outcoins};
+use rgb::fungible::{Asset, Invoice, IssueStructure, Outcoins, SelectionStrategy};
 use rgb::i9n::*;
 use rgb::rgbd::ContractName;
-use rgb::util::SealSpec;
+use rgb::util::{RevealSpec, SealSpec};
 
 trait CReturnType: Sized + 'static {
     fn from_opaque(other: &COpaqueStruct) -> Result<&mut Self, String> {
@@ -192,13 +196,20 @@ struct IssueArgs {
     dust_limit: Option<Amount>,
 }
 
-fn _issue(runtime: &COpaqueStruct, json: *mut c_char) -> Result<(), String> {
+#[derive(Debug, Serialize)]
+struct IssueResult {
+    asset: Asset,
+    /// Bech32-encoded genesis for sharing with other parties
+    genesis: String,
+}
+
+fn _issue(runtime: &COpaqueStruct, json: *mut c_char) -> Result<String, String> {
     let runtime = Runtime::from_opaque(runtime)?;
     let data: IssueArgs =
         serde_json::from_str(ptr_to_string(json)?.as_str()).map_err(|e| format!("{:?}", e))?;
     info!("{:?}", data);
 
-    runtime
+    let (asset, genesis) = runtime
         .issue(
             data.network,
             data.ticker,
@@ -210,23 +221,53 @@ fn _issue(runtime: &COpaqueStruct, json: *mut c_char) -> Result<(), String> {
             data.prune_seals,
             data.dust_limit,
         )
-        .map_err(|e| format!("{:?}", e))
+        .map_err(|e| format!("{:?}", e))?;
+
+    serde_json::to_string(&IssueResult {
+        asset,
+        genesis: genesis.to_string(),
+    })
+    .map_err(|e| format!("{:?}", e))
 }
 
 #[no_mangle]
 pub extern "C" fn issue(runtime: &COpaqueStruct, json: *mut c_char) -> CResult {
-    _issue(runtime, json).into()
+    match _issue(runtime, json) {
+        // Issue result is returned as a JSON C string, so the caller can read
+        // it in the same way as the error messages
+        Ok(result) => CResult {
+            result: CResultValue::Ok,
+            inner: COpaqueStruct::raw(string_to_ptr(result)),
+        },
+        Err(err) => Err::<(), _>(err).into(),
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct TransferArgs {
+    #[serde(default)]
     inputs: Vec<OutPoint>,
+    #[serde(default)]
+    selection: SelectionStrategy,
     allocate: Vec<Outcoins>,
     #[serde(with = "serde_with::rust::display_fromstr")]
     invoice: Invoice,
+    /// Public key of the receiver known in advance; if given, the invoice
+    /// must be signed with it
+    #[serde(default)]
+    receiver_pubkey: Option<String>,
     prototype_psbt: String,
     fee: u64,
+    #[serde(default)]
     change: Option<seal::Confidential>,
+    /// Change output which will be blinded automatically, if `change` is not
+    /// provided
+    #[serde(default)]
+    change_outpoint: Option<OutPoint>,
+    /// Amount of satoshis sent to the new output when paying to address
+    /// invoices
+    #[serde(default)]
+    address_sats: Option<u64>,
     consignment_file: String,
     transaction_file: String,
 }
@@ -237,14 +278,33 @@ fn _transfer(runtime: &COpaqueStruct, json: *mut c_char) -> Result<(), String> {
         serde_json::from_str(ptr_to_string(json)?.as_str()).map_err(|e| format!("{:?}", e))?;
     info!("{:?}", data);
 
+    let change = match (data.change, data.change_outpoint) {
+        (Some(change), _) => Some(change),
+        (None, Some(outpoint)) => Some(
+            runtime
+                .blind_outpoint(outpoint)
+                .map_err(|e| format!("{:?}", e))?,
+        ),
+        (None, None) => None,
+    };
+
+    let receiver_pubkey = data
+        .receiver_pubkey
+        .as_ref()
+        .map(|pubkey| secp256k1::PublicKey::from_str(pubkey).map_err(|e| format!("{:?}", e)))
+        .transpose()?;
+
     runtime
         .transfer(
             data.inputs,
+            data.selection,
             data.allocate,
             data.invoice,
+            receiver_pubkey,
             data.prototype_psbt,
             data.fee,
-            data.change,
+            change,
+            data.address_sats,
             data.consignment_file,
             data.transaction_file,
         )
@@ -257,3 +317,104 @@ fn _transfer(runtime: &COpaqueStruct, json: *mut c_char) -> Result<(), String> {
 pub extern "C" fn transfer(runtime: &COpaqueStruct, json: *mut c_char) -> CResult {
     _transfer(runtime, json).into()
 }
+
+#[derive(Debug, Deserialize)]
+struct PruneArgs {
+    #[serde(with = "serde_with::rust::display_fromstr")]
+    contract_id: ContractId,
+    prune_right: OutPoint,
+    inputs: Vec<OutPoint>,
+    #[serde(default)]
+    remainder: Vec<Outcoins>,
+    #[serde(default)]
+    next_prune: Option<SealSpec>,
+    #[serde(default)]
+    proof: Option<String>,
+    prototype_psbt: String,
+    fee: u64,
+    consignment_file: String,
+    transaction_file: String,
+}
+
+fn _prune(runtime: &COpaqueStruct, json: *mut c_char) -> Result<(), String> {
+    let runtime = Runtime::from_opaque(runtime)?;
+    let data: PruneArgs =
+        serde_json::from_str(ptr_to_string(json)?.as_str()).map_err(|e| format!("{:?}", e))?;
+    info!("{:?}", data);
+
+    let proof = data
+        .proof
+        .map(|proof| Vec::<u8>::from_hex(&proof))
+        .transpose()
+        .map_err(|e| format!("{:?}", e))?;
+
+    runtime
+        .prune(
+            data.contract_id,
+            data.prune_right,
+            data.inputs,
+            data.remainder,
+            data.next_prune,
+            proof,
+            data.prototype_psbt,
+            data.fee,
+            data.consignment_file,
+            data.transaction_file,
+        )
+        .map_err(|e| format!("{:?}", e))
+}
+
+#[no_mangle]
+pub extern "C" fn prune(runtime: &COpaqueStruct, json: *mut c_char) -> CResult {
+    _prune(runtime, json).into()
+}
+
+#[derive(Debug, Deserialize)]
+struct AcceptArgs {
+    consignment_file: String,
+    /// Reveal data for the outpoints blinded outside of the node
+    #[serde(default)]
+    reveal_outpoints: Vec<RevealSpec>,
+    /// Paying to address invoices paid with the consignment
+    #[serde(default)]
+    invoices: Vec<String>,
+    /// Hex-encoded witness transactions paying to the invoiced addresses
+    #[serde(default)]
+    witness_txs: Vec<String>,
+}
+
+fn _accept(runtime: &COpaqueStruct, json: *mut c_char) -> Result<(), String> {
+    let runtime = Runtime::from_opaque(runtime)?;
+    let data: AcceptArgs =
+        serde_json::from_str(ptr_to_string(json)?.as_str()).map_err(|e| format!("{:?}", e))?;
+    info!("{:?}", data);
+
+    let invoices = data
+        .invoices
+        .iter()
+        .map(|invoice| Invoice::from_str(invoice).map_err(|e| format!("{:?}", e)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let witness_txs = data
+        .witness_txs
+        .iter()
+        .map(|hex| {
+            Vec::<u8>::from_hex(hex)
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|raw| deserialize(&raw).map_err(|e| format!("{:?}", e)))
+        })
+        .collect::<Result<Vec<Transaction>, String>>()?;
+
+    runtime
+        .accept(
+            data.consignment_file,
+            data.reveal_outpoints,
+            invoices,
+            witness_txs,
+        )
+        .map_err(|e| format!("{:?}", e))
+}
+
+#[no_mangle]
+pub extern "C" fn accept(runtime: &COpaqueStruct, json: *mut c_char) -> CResult {
+    _accept(runtime, json).into()
+}
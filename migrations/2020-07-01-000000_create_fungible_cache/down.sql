@@ -0,0 +1,4 @@
+DROP INDEX allocations_outpoint;
+DROP TABLE allocations;
+DROP TABLE issues;
+DROP TABLE assets;
@@ -0,0 +1,36 @@
+CREATE TABLE assets (
+    id VARCHAR(64) NOT NULL PRIMARY KEY,
+    ticker TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    network TEXT NOT NULL,
+    fractional_bits SMALLINT NOT NULL,
+    date TIMESTAMP NOT NULL,
+    dust_limit TEXT NOT NULL,
+    known_circulating TEXT NOT NULL,
+    total_supply TEXT,
+    unspent_issue_txo TEXT
+);
+
+CREATE TABLE issues (
+    asset_id VARCHAR(64) NOT NULL REFERENCES assets (id) ON DELETE CASCADE,
+    series INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    issue_id VARCHAR(64) NOT NULL,
+    txo TEXT,
+    supply TEXT NOT NULL,
+    PRIMARY KEY (asset_id, series, position)
+);
+
+CREATE TABLE allocations (
+    asset_id VARCHAR(64) NOT NULL REFERENCES assets (id) ON DELETE CASCADE,
+    txid VARCHAR(64) NOT NULL,
+    vout INTEGER NOT NULL,
+    node_id VARCHAR(64) NOT NULL,
+    assignment_index INTEGER NOT NULL,
+    amount TEXT NOT NULL,
+    revealed_amount TEXT NOT NULL,
+    PRIMARY KEY (asset_id, txid, vout, node_id, assignment_index)
+);
+
+CREATE INDEX allocations_outpoint ON allocations (txid, vout);
@@ -0,0 +1 @@
+DROP TABLE prune_rights;
@@ -0,0 +1,8 @@
+CREATE TABLE prune_rights (
+    asset_id VARCHAR(64) NOT NULL REFERENCES assets (id) ON DELETE CASCADE,
+    txid VARCHAR(64) NOT NULL,
+    vout INTEGER NOT NULL,
+    node_id VARCHAR(64) NOT NULL,
+    assignment_index INTEGER NOT NULL,
+    PRIMARY KEY (asset_id, txid, vout)
+);
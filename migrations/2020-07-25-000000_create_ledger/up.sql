@@ -0,0 +1,13 @@
+CREATE TABLE ledger (
+    asset_id VARCHAR(64) NOT NULL REFERENCES assets (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    operation VARCHAR(16) NOT NULL,
+    node_id VARCHAR(64) NOT NULL,
+    txid VARCHAR(64) NOT NULL,
+    outpoint TEXT,
+    amount TEXT NOT NULL,
+    counterparty VARCHAR(64),
+    timestamp TIMESTAMP NOT NULL,
+    pending BOOLEAN NOT NULL DEFAULT FALSE,
+    PRIMARY KEY (asset_id, position)
+);
@@ -15,11 +15,12 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use lnpbp::bitcoin::util::psbt::PartiallySignedTransaction;
-use lnpbp::bitcoin::OutPoint;
+use lnpbp::bitcoin::{OutPoint, Transaction, Txid};
+use lnpbp::bp;
 use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
-use lnpbp::rgb::{Amount, Consignment, ContractId};
+use lnpbp::rgb::{Amount, Consignment, ContractId, NodeId};
 
-use crate::fungible::{Outcoincealed, Outcoins};
+use crate::fungible::{AccountingAmount, Invoice, Outcoincealed, Outcoins, SelectionStrategy};
 use crate::util::SealSpec;
 
 #[derive(Clone, Debug, Display, LnpApi)]
@@ -48,10 +49,54 @@ pub enum Request {
     #[lnp_api(type = 0x010d)]
     Forget(::lnpbp::bitcoin::OutPoint),
 
+    #[lnp_api(type = 0x010f)]
+    Inflate(crate::api::fungible::InflateApi),
+
+    #[lnp_api(type = 0x0111)]
+    Prune(crate::api::fungible::PruneApi),
+
+    #[lnp_api(type = 0x0113)]
+    Blind(::lnpbp::bitcoin::OutPoint),
+
+    #[lnp_api(type = 0x0115)]
+    Balance(::lnpbp::rgb::ContractId),
+
+    #[lnp_api(type = 0x0117)]
+    History(::lnpbp::rgb::ContractId),
+
+    #[lnp_api(type = 0x0119)]
+    CancelTransfer(::lnpbp::rgb::NodeId),
+
     #[lnp_api(type = 0xFF01)]
     Sync,
 }
 
+/// Events published by fungibled over its PUB socket, informing clients
+/// about the asset cache updates
+#[derive(Clone, Debug, Display, LnpApi)]
+#[lnp_api(encoding = "strict")]
+#[display_from(Debug)]
+#[non_exhaustive]
+pub enum Notification {
+    #[lnp_api(type = 0x0A01)]
+    AssetImported(::lnpbp::rgb::ContractId),
+
+    #[lnp_api(type = 0x0A03)]
+    AssetIssued(::lnpbp::rgb::ContractId),
+
+    #[lnp_api(type = 0x0A05)]
+    TransferCreated(crate::api::fungible::TransferCreated),
+
+    #[lnp_api(type = 0x0A07)]
+    ConsignmentAccepted(crate::api::fungible::ConsignmentAccepted),
+
+    #[lnp_api(type = 0x0A09)]
+    AllocationForgotten(::lnpbp::bitcoin::OutPoint),
+
+    #[lnp_api(type = 0x0A0B)]
+    TransferCancelled(::lnpbp::rgb::NodeId),
+}
+
 #[derive(
     Clap, Clone, PartialEq, Serialize, Deserialize, StrictEncode, StrictDecode, Debug, Display,
 )]
@@ -70,7 +115,7 @@ pub struct Issue {
 
     /// Limit for the total supply; ignored if the asset can't be inflated
     #[clap(short, long)]
-    pub supply: Option<f32>,
+    pub supply: Option<AccountingAmount>,
 
     /// Enables secondary issuance/inflation; takes UTXO seal definition
     /// as its value
@@ -85,6 +130,11 @@ pub struct Issue {
     #[clap(short = "D", long)]
     pub dust_limit: Option<Amount>,
 
+    /// Assigns right to prune (burn) the asset to a given UTXO seal
+    /// definition; may be repeated
+    #[clap(short = "P", long, number_of_values = 1)]
+    pub prune: Vec<SealSpec>,
+
     /// Asset allocation, in form of <amount>@<txid>:<vout>
     #[clap(required = true)]
     pub allocate: Vec<Outcoins>,
@@ -96,12 +146,20 @@ pub struct TransferApi {
     /// Asset contract id
     pub contract_id: ContractId,
 
+    /// Network the base layer transaction is constructed for; must match
+    /// the network the node is running on
+    pub network: bp::Network,
+
     /// Base layer transaction structure to use
     pub psbt: PartiallySignedTransaction,
 
-    /// Asset input: unspent transaction outputs
+    /// Asset input: unspent transaction outputs. If empty, inputs are
+    /// selected automatically from the known asset allocations
     pub inputs: Vec<OutPoint>,
 
+    /// Strategy used for the automatic input selection
+    pub selection: SelectionStrategy,
+
     /// Asset change allocations
     ///
     /// Here we always know an explicit outpoint that will contain the assets
@@ -114,18 +172,89 @@ pub struct TransferApi {
     /// blinding entropy.
     pub theirs: Vec<Outcoincealed>,
 
+    /// Receiver's allocations to the new outputs of the witness transaction,
+    /// used for paying to address invoices. These seals are defined by the
+    /// output number only, and are revealed to the receiver in the
+    /// consignment
+    pub theirs_witness: Vec<Outcoins>,
+
     /// Optional change output: the rest of assets will be allocated here
     pub change: Option<OutpointHash>,
 }
 
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display_from(Debug)]
+pub struct InflateApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Network the base layer transaction is constructed for; must match
+    /// the network the node is running on
+    pub network: bp::Network,
+
+    /// Base layer transaction structure to use; it must spend the output
+    /// holding the issue control right of the asset
+    pub psbt: PartiallySignedTransaction,
+
+    /// Allocations of the newly issued supply
+    pub allocate: Vec<Outcoins>,
+
+    /// Seal receiving issue control right for the next secondary issuance;
+    /// if none, the asset can't be inflated anymore
+    pub next_issue: Option<SealSpec>,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display_from(Debug)]
+pub struct PruneApi {
+    /// Asset contract id
+    pub contract_id: ContractId,
+
+    /// Network the base layer transaction is constructed for; must match
+    /// the network the node is running on
+    pub network: bp::Network,
+
+    /// Base layer transaction structure to use; it must spend the output
+    /// holding the prune right and all pruned asset inputs
+    pub psbt: PartiallySignedTransaction,
+
+    /// Transaction output holding the prune right
+    pub prune_right: OutPoint,
+
+    /// Asset inputs to prune: unspent transaction outputs
+    pub inputs: Vec<OutPoint>,
+
+    /// Allocations for the part of the input assets which is not burned
+    pub remainder: Vec<Outcoins>,
+
+    /// Seal receiving the prune right after the operation; if none, the
+    /// prune right is destroyed
+    pub next_prune: Option<SealSpec>,
+
+    /// Optional proof of the prune operation validity, recorded into the
+    /// state transition metadata
+    pub proof: Option<Vec<u8>>,
+}
+
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
 #[display_from(Debug)]
 pub struct AcceptApi {
     /// Raw consignment data
     pub consignment: Consignment,
 
-    /// Reveal outpoints data used during invoice creation
+    /// Reveal outpoints data used during invoice creation. Reveals for the
+    /// outpoints blinded by the node itself are found automatically and
+    /// do not need to be provided
     pub reveal_outpoints: Vec<OutpointReveal>,
+
+    /// Paying to address invoices issued by the receiver. Assets assigned to
+    /// the witness transaction outputs are credited only if the output pays
+    /// to the address of one of these invoices
+    pub invoices: Vec<Invoice>,
+
+    /// Witness transactions containing outputs which pay to the invoiced
+    /// addresses; required to check the output scripts
+    pub witness_txs: Vec<Transaction>,
 }
 
 fn ticker_validator(name: &str) -> Result<(), String> {
@@ -141,3 +270,22 @@ fn ticker_validator(name: &str) -> Result<(), String> {
         Ok(())
     }
 }
+
+/// Details of the transfer (as well as inflation or pruning) prepared by the
+/// node; the witness transaction still has to be signed and published by
+/// the wallet
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display_from(Debug)]
+pub struct TransferCreated {
+    pub contract_id: ContractId,
+    pub node_id: NodeId,
+    pub txid: Txid,
+}
+
+#[derive(Clone, PartialEq, StrictEncode, StrictDecode, Debug, Display)]
+#[display_from(Debug)]
+pub struct ConsignmentAccepted {
+    pub contract_id: ContractId,
+    /// State transitions which have allocated assets to our seals
+    pub node_ids: Vec<NodeId>,
+}
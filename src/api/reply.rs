@@ -11,13 +11,22 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use core::iter;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use lnpbp::bitcoin::hashes::Hash;
 use lnpbp::bitcoin::util::psbt::PartiallySignedTransaction as Psbt;
+use lnpbp::bitcoin::{OutPoint, Txid};
 use lnpbp::data_format::DataFormat;
 use lnpbp::lnp;
-use lnpbp::rgb::Consignment;
+use lnpbp::rgb::{
+    validation, Amount, AnchorId, Consignment, ContractId, Genesis, Node, NodeId, Validity,
+};
 
 #[cfg(feature = "service")]
 use crate::error::{RuntimeError, ServiceError};
+use crate::fungible::Asset;
 
 #[derive(Clone, Debug, Display, LnpApi)]
 #[lnp_api(encoding = "strict")]
@@ -45,8 +54,35 @@ pub enum Reply {
 
     #[lnp_api(type = 0xFF09)]
     Transfer(crate::api::reply::Transfer),
-    //#[lnp_api(type = 0xFF0B)]
-    //ValidationStatus(::lnpbp::rgb::validation::Status),
+    #[lnp_api(type = 0xFF0B)]
+    ValidationStatus(crate::api::reply::ValidationStatus),
+
+    #[lnp_api(type = 0xFF0D)]
+    Forgotten(crate::api::reply::Forgotten),
+
+    #[lnp_api(type = 0xFF0F)]
+    SchemaIds(Vec<::lnpbp::rgb::SchemaId>),
+
+    #[lnp_api(type = 0xFF11)]
+    Schemata(Vec<::lnpbp::rgb::Schema>),
+
+    #[lnp_api(type = 0xFF13)]
+    ContractIds(Vec<::lnpbp::rgb::ContractId>),
+
+    #[lnp_api(type = 0xFF15)]
+    BlindedOutpoint(::lnpbp::bp::blind::OutpointHash),
+
+    #[lnp_api(type = 0xFF17)]
+    Balance(crate::api::reply::Balance),
+
+    #[lnp_api(type = 0xFF19)]
+    History(Vec<crate::fungible::LedgerEntry>),
+
+    #[lnp_api(type = 0xFF1B)]
+    Issue(crate::api::reply::Issue),
+
+    #[lnp_api(type = 0xFF1D)]
+    Network(::lnpbp::bp::Network),
 }
 
 impl From<lnp::presentation::Error> for Reply {
@@ -86,6 +122,152 @@ pub struct Transfer {
     pub psbt: Psbt,
 }
 
+/// Strict-encodable representation of the consignment validation status
+/// (`lnpbp::rgb::validation::Status`), which is not encodable itself
+#[derive(
+    Clone, PartialEq, Eq, Debug, Display, Serialize, Deserialize, StrictEncode, StrictDecode,
+)]
+#[display_from(Debug)]
+pub struct ValidationStatus {
+    pub unresolved_txids: Vec<Txid>,
+    pub failures: Vec<String>,
+    pub warnings: Vec<String>,
+    pub info: Vec<String>,
+    /// Validation results for each of the consignment nodes (genesis and
+    /// state transitions)
+    pub nodes: Vec<NodeValidation>,
+}
+
+/// Failures and warnings related to a specific consignment node
+#[derive(
+    Clone, PartialEq, Eq, Debug, Display, Serialize, Deserialize, StrictEncode, StrictDecode,
+)]
+#[display_from(Debug)]
+pub struct NodeValidation {
+    pub node_id: NodeId,
+    pub failures: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+impl ValidationStatus {
+    pub fn with(status: &validation::Status, consignment: &Consignment) -> Self {
+        // Failures and warnings are assigned to the node if they refer to its id
+        let failures: Vec<(Option<NodeId>, String)> = status
+            .failures
+            .iter()
+            .map(|f| (failure_node_id(f), format!("{:?}", f)))
+            .collect();
+        let warnings: Vec<(Option<NodeId>, String)> = status
+            .warnings
+            .iter()
+            .map(|w| (warning_node_id(w), format!("{:?}", w)))
+            .collect();
+        let filter = |list: &Vec<(Option<NodeId>, String)>, node_id: NodeId| -> Vec<String> {
+            list.iter()
+                .filter(|(id, _)| *id == Some(node_id))
+                .map(|(_, msg)| msg.clone())
+                .collect()
+        };
+
+        let genesis_id = NodeId::from_inner(consignment.genesis.contract_id().into_inner());
+        let nodes = iter::once(genesis_id)
+            .chain(
+                consignment
+                    .data
+                    .iter()
+                    .map(|(_, transition)| transition.node_id()),
+            )
+            .map(|node_id| NodeValidation {
+                node_id,
+                failures: filter(&failures, node_id),
+                warnings: filter(&warnings, node_id),
+            })
+            .collect();
+
+        Self {
+            unresolved_txids: status.unresolved_txids.clone(),
+            info: status.info.iter().map(|i| format!("{:?}", i)).collect(),
+            failures: failures.into_iter().map(|(_, msg)| msg).collect(),
+            warnings: warnings.into_iter().map(|(_, msg)| msg).collect(),
+            nodes,
+        }
+    }
+
+    pub fn validity(&self) -> Validity {
+        if !self.failures.is_empty() {
+            Validity::Invalid
+        } else if !self.unresolved_txids.is_empty() {
+            Validity::UnresolvedTransactions
+        } else {
+            Validity::Valid
+        }
+    }
+}
+
+/// Node which the validation failure refers to; failures which are not
+/// related to a specific node are reported for the consignment as a whole
+fn failure_node_id(failure: &validation::Failure) -> Option<NodeId> {
+    use validation::Failure::*;
+    match failure {
+        TransitionAbsent(node_id)
+        | TransitionNotAnchored(node_id)
+        | EndpointTransitionNotFound(node_id)
+        | WitnessNoCommitment(node_id, ..)
+        | SchemaUnknownTransitionType(node_id, _)
+        | SchemaUnknownFieldType(node_id, _)
+        | SchemaUnknownAssignmentType(node_id, _) => Some(*node_id),
+        TransitionParentWrongSealType { node_id, .. }
+        | TransitionParentWrongSeal { node_id, .. }
+        | TransitionParentConfidentialSeal { node_id, .. }
+        | TransitionParentIsNotWitnessInput { node_id, .. } => Some(*node_id),
+        _ => None,
+    }
+}
+
+/// Node which the validation warning refers to, if any
+fn warning_node_id(warning: &validation::Warning) -> Option<NodeId> {
+    use validation::Warning::*;
+    match warning {
+        EndpointDuplication(node_id, _)
+        | EndpointTransitionSealNotFound(node_id, _)
+        | ExcessiveTransition(node_id) => Some(*node_id),
+        _ => None,
+    }
+}
+
+/// Asset balance of the wallet, in atomic units
+#[derive(
+    Clone, PartialEq, Eq, Debug, Display, Serialize, Deserialize, StrictEncode, StrictDecode,
+)]
+#[display_from(Debug)]
+pub struct Balance {
+    pub contract_id: ContractId,
+    /// Total amount of the allocations which are not spent by our transfers
+    pub available: Amount,
+    /// Amount of the allocations spent by our transfers which are not yet
+    /// forgotten
+    pub spent: Amount,
+    /// Amounts for each of the unspent transaction outputs
+    pub allocations: BTreeMap<OutPoint, Amount>,
+}
+
+/// Newly issued asset together with its genesis, which has to be shared with
+/// the other parties for importing the asset
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode)]
+#[display_from(Debug)]
+pub struct Issue {
+    pub asset: Asset,
+    pub genesis: Genesis,
+}
+
+/// Summary of the data removed from the stash by the forget (prune) request
+#[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
+#[display_from(Debug)]
+pub struct Forgotten {
+    pub transitions: Vec<NodeId>,
+    pub anchors: Vec<AnchorId>,
+}
+
 #[derive(Clone, Debug, Display, StrictEncode, StrictDecode, Error)]
 #[display_from(Debug)]
 #[non_exhaustive]
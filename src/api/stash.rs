@@ -55,6 +55,34 @@ pub enum Request {
 
     #[lnp_api(type = 0x0407)]
     Forget(Vec<(::lnpbp::rgb::NodeId, u16)>),
+
+    /// Validates consignment and, if it is valid, stores its genesis, state
+    /// transitions and anchors in the stash
+    #[lnp_api(type = 0x0409)]
+    Import(::lnpbp::rgb::Consignment),
+
+    /// Reports the network stashd is configured for, so the daemons using
+    /// the stash can make sure they run on the same one
+    #[lnp_api(type = 0x0501)]
+    Network(),
+}
+
+/// Events published by stashd over its PUB socket on each stash update
+#[derive(Clone, Debug, Display, LnpApi)]
+#[lnp_api(encoding = "strict")]
+#[display_from(Debug)]
+#[non_exhaustive]
+pub enum Notification {
+    #[lnp_api(type = 0x0B01)]
+    GenesisAdded(::lnpbp::rgb::ContractId),
+
+    /// Genesis, state transitions and anchors of the consignment are merged
+    /// into the stash
+    #[lnp_api(type = 0x0B03)]
+    ConsignmentMerged(::lnpbp::rgb::ContractId),
+
+    #[lnp_api(type = 0x0B05)]
+    Forgotten(crate::api::reply::Forgotten),
 }
 
 #[derive(Clone, StrictEncode, StrictDecode, Debug, Display)]
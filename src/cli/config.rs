@@ -19,7 +19,7 @@ use std::path::PathBuf;
 use lnpbp::bp;
 use lnpbp::lnp::transport::zmq::SocketLocator;
 
-use super::{fungible, Error, Runtime};
+use super::{fungible, stash, Error, Runtime};
 use crate::constants::*;
 
 #[derive(Clap, Clone, Debug, Display)]
@@ -43,6 +43,14 @@ pub struct Opts {
     #[clap(short, long, default_value = FUNGIBLED_RPC_ENDPOINT)]
     pub endpoint: String,
 
+    /// PUB/SUB endpoint of contracts service, used to receive notifications
+    #[clap(long, default_value = FUNGIBLED_PUB_ENDPOINT, env = "RGB_FUNGIBLED_PUB")]
+    pub pub_endpoint: String,
+
+    /// RPC endpoint of stash service
+    #[clap(long, default_value = STASHD_RPC_ENDPOINT, env = "RGB_STASHD_RPC")]
+    pub stash_endpoint: String,
+
     /// Command to execute
     #[clap(subcommand)]
     pub command: Command,
@@ -61,6 +69,13 @@ pub enum Command {
         #[clap(subcommand)]
         subcommand: fungible::Command,
     },
+
+    /// Reading data from RGB stash
+    Stash {
+        /// Subcommand specifying particular operation
+        #[clap(subcommand)]
+        subcommand: stash::Command,
+    },
 }
 
 // We need config structure since not all of the parameters can be specified
@@ -72,6 +87,8 @@ pub struct Config {
     pub verbose: u8,
     pub data_dir: PathBuf,
     pub endpoint: SocketLocator,
+    pub pub_endpoint: SocketLocator,
+    pub stash_endpoint: SocketLocator,
     pub network: bp::Network,
 }
 
@@ -84,6 +101,8 @@ impl From<Opts> for Config {
         };
         me.data_dir = me.parse_param(opts.data_dir);
         me.endpoint = me.parse_param(opts.endpoint);
+        me.pub_endpoint = me.parse_param(opts.pub_endpoint);
+        me.stash_endpoint = me.parse_param(opts.stash_endpoint);
         me
     }
 }
@@ -98,6 +117,12 @@ impl Default for Config {
             endpoint: FUNGIBLED_RPC_ENDPOINT
                 .parse()
                 .expect("Broken FUNGIBLED_RPC_ENDPOINT value"),
+            pub_endpoint: FUNGIBLED_PUB_ENDPOINT
+                .parse()
+                .expect("Broken FUNGIBLED_PUB_ENDPOINT value"),
+            stash_endpoint: STASHD_RPC_ENDPOINT
+                .parse()
+                .expect("Broken STASHD_RPC_ENDPOINT value"),
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
@@ -109,6 +134,7 @@ impl Command {
     pub fn exec(self, runtime: Runtime) -> Result<(), Error> {
         match self {
             Command::Fungible { subcommand, .. } => subcommand.exec(runtime),
+            Command::Stash { subcommand, .. } => subcommand.exec(runtime),
         }
     }
 }
@@ -38,6 +38,12 @@ pub enum Error {
     #[derive_from]
     ConsensusEncoding(lnpbp::bitcoin::consensus::encode::Error),
 
+    #[derive_from(lnpbp::bitcoin::hashes::hex::Error)]
+    HexDecoding,
+
+    #[derive_from]
+    Invoice(crate::fungible::InvoiceError),
+
     DataInconsistency,
 
     UnsupportedFunctionality,
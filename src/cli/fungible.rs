@@ -11,26 +11,36 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use chrono::{Duration, Utc};
 use clap::Clap;
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 
 use bitcoin::consensus::{Decodable, Encodable};
+use bitcoin::secp256k1::{self, rand::RngCore};
 use bitcoin::util::psbt::{raw::Key, PartiallySignedTransaction};
 use bitcoin::OutPoint;
 
 use lnpbp::bitcoin;
-use lnpbp::bp::blind::OutpointReveal;
+use lnpbp::bitcoin::hashes::hex::{FromHex, ToHex};
+use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
 use lnpbp::client_side_validation::Conceal;
 use lnpbp::data_format::DataFormat;
 use lnpbp::rgb::prelude::*;
+use lnpbp::rgb::Validity;
+use lnpbp::strict_encoding::{strict_decode, strict_encode};
 
 use super::{Error, OutputFormat, Runtime};
-use crate::api::fungible::{AcceptApi, Issue, TransferApi};
+use crate::api::fungible::{AcceptApi, InflateApi, Issue, Notification, PruneApi, TransferApi};
 use crate::api::{reply, Reply};
-use crate::fungible::{Asset, Invoice, Outcoincealed, Outcoins, Outpoint};
+use crate::constants::{FUNGIBLED_NOTIFICATION_TIMEOUT, RGB20_ADDRESS_OUTPUT_SATS};
+use crate::fungible::{
+    AccountingAmount, Asset, Invoice, InvoiceError, Outcoincealed, Outcoins, Outpoint,
+    OutpointDescriptor, SelectionStrategy,
+};
 use crate::util::file::ReadWrite;
+use crate::util::{RevealSpec, SealSpec};
 
 #[derive(Clap, Clone, Debug, Display)]
 #[display_from(Debug)]
@@ -65,8 +75,18 @@ pub enum Command {
     /// Do a transfer of some requested asset to another party
     Transfer(TransferCli),
 
-    /// Do a transfer of some requested asset to another party
+    /// Issues additional supply of an inflatable asset (secondary issuance)
+    Inflate(InflateCli),
+
+    /// Burns (prunes) asset allocations using the prune right
+    Prune(PruneCli),
+
+    /// Validates incoming transfer consignment
     Validate {
+        /// Format for validation status output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
         /// Consignment file
         consignment: PathBuf,
     },
@@ -76,11 +96,40 @@ pub enum Command {
         /// Consignment file
         consignment: PathBuf,
 
-        /// Locally-controlled outpoint (specified when the invoice was created)
-        outpoint: OutPoint,
+        /// Reveal data for the locally-controlled outpoints blinded outside
+        /// of the node, in form of <blinding>@<txid>:<vout>; may be repeated
+        #[clap(short, long = "reveal", number_of_values = 1)]
+        reveals: Vec<RevealSpec>,
+
+        /// Paying to address invoices issued by us, which are paid with the
+        /// consignment; may be repeated
+        #[clap(short, long = "invoice", number_of_values = 1)]
+        invoices: Vec<Invoice>,
+
+        /// Files with the witness transactions paying to the invoiced
+        /// addresses; may be repeated
+        #[clap(short, long = "witness", number_of_values = 1)]
+        witness: Vec<PathBuf>,
+    },
+
+    /// Shows asset balance of the wallet
+    Balance {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
+
+        /// Bech32 representation of the asset ID (contract id of the asset genesis)
+        asset: ContractId,
+    },
+
+    /// Shows history of the incoming and outgoing asset transfers
+    History {
+        /// Format for information output
+        #[clap(short, long, arg_enum, default_value = "yaml")]
+        format: OutputFormat,
 
-        /// Outpoint blinding factor (generated when the invoice was created)
-        blinding_factor: u64,
+        /// Bech32 representation of the asset ID (contract id of the asset genesis)
+        asset: ContractId,
     },
 
     Forget {
@@ -88,6 +137,13 @@ pub enum Command {
         /// has to be forgotten
         outpoint: OutPoint,
     },
+
+    /// Cancels our pending transfer which witness transaction was not
+    /// published, making its inputs available for spending again
+    Cancel {
+        /// Id of the transfer state transition
+        node_id: NodeId,
+    },
 }
 
 #[derive(Clap, Clone, PartialEq, Debug, Display)]
@@ -97,19 +153,39 @@ pub struct InvoiceCli {
     pub asset: ContractId,
 
     /// Amount
-    pub amount: f32,
+    pub amount: AccountingAmount,
 
     /// Receive assets to a given bitcoin address or UTXO
-    pub outpoint: OutPoint,
+    pub outpoint: OutpointDescriptor,
+
+    /// Invoice expiration period, in seconds from now
+    #[clap(short, long)]
+    pub expiry: Option<u64>,
+
+    /// Description of the payment purpose
+    #[clap(short, long)]
+    pub description: Option<String>,
+
+    /// File with the secret key (in hex) for signing the invoice, proving
+    /// its origin to the payer. The key may also be provided with
+    /// `RGB_INVOICE_SIGNING_KEY` environment variable; it is never accepted
+    /// as a command-line argument
+    #[clap(long)]
+    pub signing_key_file: Option<PathBuf>,
 }
 
 #[derive(Clap, Clone, PartialEq, Debug, Display)]
 #[display_from(Debug)]
 pub struct TransferCli {
-    /// Asset inputs
+    /// Asset inputs; if none are given, inputs are selected automatically
     #[clap(short = "i", long = "input", min_values = 1)]
     pub inputs: Vec<OutPoint>,
 
+    /// Strategy for automatic input selection: largest-first,
+    /// minimize-inputs or privacy
+    #[clap(short, long, default_value = "largest-first")]
+    pub selection: SelectionStrategy,
+
     /// Adds additional asset allocations; MUST use transaction inputs
     /// controlled by the local party
     #[clap(short, long)]
@@ -118,14 +194,101 @@ pub struct TransferCli {
     /// Invoice to pay
     pub invoice: Invoice,
 
+    /// Public key of the receiver known in advance; if given, the invoice
+    /// must be signed with it
+    #[clap(long)]
+    pub receiver_pubkey: Option<secp256k1::PublicKey>,
+
+    /// Amount of satoshis sent to the new output when paying to address
+    /// invoices
+    #[clap(long, default_value = RGB20_ADDRESS_OUTPUT_SATS)]
+    pub sats: u64,
+
     /// Read partially-signed transaction prototype
     pub prototype: PathBuf,
 
     /// Fee (in satoshis)
     pub fee: u64,
 
-    /// Change output
-    // pub change: OutpointHash,
+    /// Change output; it is blinded by the node, which keeps the blinding
+    /// data for accepting the change
+    #[clap(short, long, conflicts_with = "change-blinded")]
+    pub change: Option<OutPoint>,
+
+    /// Already blinded change output
+    #[clap(long)]
+    pub change_blinded: Option<OutpointHash>,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display_from(Debug)]
+pub struct InflateCli {
+    /// Asset to inflate
+    pub asset: ContractId,
+
+    /// Allocations of the newly issued supply, in form of
+    /// <amount>@[<txid>:]<vout>
+    #[clap(short, long, min_values = 1, required = true)]
+    pub allocate: Vec<Outcoins>,
+
+    /// Seal receiving the right for the next secondary issuance, in form of
+    /// [<txid>:]<vout>; if omitted, asset can't be inflated anymore
+    #[clap(short, long)]
+    pub next_issue: Option<SealSpec>,
+
+    /// Read partially-signed transaction prototype; it must spend the output
+    /// holding the asset issue control right
+    pub prototype: PathBuf,
+
+    /// Fee (in satoshis)
+    pub fee: u64,
+
+    /// File to save consignment to
+    pub consignment: PathBuf,
+
+    /// File to save updated partially-signed bitcoin transaction to
+    pub transaction: PathBuf,
+}
+
+#[derive(Clap, Clone, PartialEq, Debug, Display)]
+#[display_from(Debug)]
+pub struct PruneCli {
+    /// Asset to prune
+    pub asset: ContractId,
+
+    /// Transaction output holding the prune right
+    pub prune_right: OutPoint,
+
+    /// Asset inputs to burn
+    #[clap(short = "i", long = "input", min_values = 1, required = true)]
+    pub inputs: Vec<OutPoint>,
+
+    /// Allocations for the part of input assets that must not be burned, in
+    /// form of <amount>@[<txid>:]<vout>
+    #[clap(short, long)]
+    pub allocate: Vec<Outcoins>,
+
+    /// Seal receiving the prune right after the operation, in form of
+    /// [<txid>:]<vout>; if omitted, the prune right is destroyed
+    #[clap(short, long)]
+    pub next_prune: Option<SealSpec>,
+
+    /// Hex-encoded proof of the prune operation validity
+    #[clap(long)]
+    pub proof: Option<String>,
+
+    /// Read partially-signed transaction prototype; it must spend the output
+    /// holding the prune right and all the asset inputs
+    pub prototype: PathBuf,
+
+    /// Fee (in satoshis)
+    pub fee: u64,
 
     /// File to save consignment to
     pub consignment: PathBuf,
@@ -143,15 +306,28 @@ impl Command {
             Command::Invoice(invoice) => invoice.exec(runtime),
             Command::Issue(issue) => issue.exec(runtime),
             Command::Transfer(transfer) => transfer.exec(runtime),
-            Command::Validate { ref consignment } => {
-                self.exec_validate(runtime, consignment.clone())
-            }
+            Command::Inflate(inflate) => inflate.exec(runtime),
+            Command::Prune(prune) => prune.exec(runtime),
+            Command::Validate {
+                format,
+                ref consignment,
+            } => self.exec_validate(runtime, format, consignment.clone()),
             Command::Accept {
                 ref consignment,
-                outpoint,
-                blinding_factor,
-            } => self.exec_accept(runtime, consignment.clone(), outpoint, blinding_factor),
+                ref reveals,
+                ref invoices,
+                ref witness,
+            } => self.exec_accept(
+                runtime,
+                consignment.clone(),
+                reveals.clone(),
+                invoices.clone(),
+                witness.clone(),
+            ),
+            Command::Balance { format, asset } => self.exec_balance(runtime, format, asset),
+            Command::History { format, asset } => self.exec_history(runtime, format, asset),
             Command::Forget { outpoint } => self.exec_forget(runtime, outpoint),
+            Command::Cancel { node_id } => self.exec_cancel(runtime, node_id),
         }
     }
 
@@ -170,7 +346,7 @@ impl Command {
                     DataFormat::Yaml => serde_yaml::from_slice(&data)?,
                     DataFormat::Json => serde_json::from_slice(&data)?,
                     DataFormat::Toml => toml::from_slice(&data)?,
-                    DataFormat::StrictEncode => unimplemented!(),
+                    DataFormat::StrictEncode => strict_decode(&data)?,
                 };
                 let short: Vec<HashMap<&str, String>> = assets
                     .iter()
@@ -197,7 +373,11 @@ impl Command {
                         long_str = toml::to_string(&assets)?;
                         short_str = toml::to_string(&short)?;
                     }
-                    _ => unimplemented!(),
+                    OutputFormat::StrictEncode => {
+                        long_str = strict_encode(&assets)?.to_hex();
+                        short_str = long_str.clone();
+                    }
+                    _ => Err(Error::UnsupportedFunctionality)?,
                 }
                 if long {
                     println!("{}", long_str);
@@ -253,7 +433,12 @@ impl Command {
         Ok(())
     }
 
-    fn exec_validate(&self, mut runtime: Runtime, filename: PathBuf) -> Result<(), Error> {
+    fn exec_validate(
+        &self,
+        mut runtime: Runtime,
+        output_format: OutputFormat,
+        filename: PathBuf,
+    ) -> Result<(), Error> {
         use lnpbp::strict_encoding::strict_encode;
 
         info!("Validating asset transfer...");
@@ -268,8 +453,20 @@ impl Command {
             Reply::Failure(failure) => {
                 eprintln!("Server returned error: {}", failure);
             }
-            Reply::Success => {
-                eprintln!("Asset transfer successfully validated.");
+            Reply::ValidationStatus(status) => {
+                match status.validity() {
+                    Validity::Valid => eprintln!("Asset transfer successfully validated."),
+                    Validity::UnresolvedTransactions => eprintln!(
+                        "Asset transfer is valid, but some of the bitcoin transactions can't be resolved."
+                    ),
+                    Validity::Invalid => eprintln!("Asset transfer is invalid."),
+                }
+                match output_format {
+                    OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&status)?),
+                    OutputFormat::Json => println!("{}", serde_json::to_string(&status)?),
+                    OutputFormat::Toml => println!("{}", toml::to_string(&status)?),
+                    _ => Err(Error::UnsupportedFunctionality)?,
+                }
             }
             _ => {
                 eprintln!(
@@ -280,12 +477,65 @@ impl Command {
         Ok(())
     }
 
+    fn exec_balance(
+        &self,
+        mut runtime: Runtime,
+        output_format: OutputFormat,
+        asset_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.balance(asset_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Balance(balance) => match output_format {
+                OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&balance)?),
+                OutputFormat::Json => println!("{}", serde_json::to_string(&balance)?),
+                OutputFormat::Toml => println!("{}", toml::to_string(&balance)?),
+                OutputFormat::StrictEncode => println!("{}", strict_encode(balance)?.to_hex()),
+                _ => Err(Error::UnsupportedFunctionality)?,
+            },
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn exec_history(
+        &self,
+        mut runtime: Runtime,
+        output_format: OutputFormat,
+        asset_id: ContractId,
+    ) -> Result<(), Error> {
+        match &*runtime.history(asset_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::History(entries) => match output_format {
+                OutputFormat::Yaml => println!("{}", serde_yaml::to_string(&entries)?),
+                OutputFormat::Json => println!("{}", serde_json::to_string(&entries)?),
+                OutputFormat::Toml => println!("{}", toml::to_string(&entries)?),
+                OutputFormat::StrictEncode => println!("{}", strict_encode(entries)?.to_hex()),
+                _ => Err(Error::UnsupportedFunctionality)?,
+            },
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn exec_accept(
         &self,
         mut runtime: Runtime,
         filename: PathBuf,
-        outpoint: OutPoint,
-        blinding_factor: u64,
+        reveals: Vec<RevealSpec>,
+        invoices: Vec<Invoice>,
+        witness: Vec<PathBuf>,
     ) -> Result<(), Error> {
         use lnpbp::strict_encoding::strict_encode;
 
@@ -297,23 +547,41 @@ impl Command {
         })?;
         trace!("{:?}", strict_encode(&consignment));
 
-        let api = if let Some((_, outpoint_hash)) = consignment.endpoints.get(0) {
-            let outpoint_reveal = OutpointReveal {
-                blinding: blinding_factor,
-                txid: outpoint.txid,
-                vout: outpoint.vout as u16,
-            };
-            if outpoint_reveal.conceal() != *outpoint_hash {
-                eprintln!("The provided outpoint and blinding factors does not match outpoint from the consignment");
+        if consignment.endpoints.is_empty() {
+            eprintln!("Consignment does not contain any endpoints which may be accepted");
+            Err(Error::UnsupportedFunctionality)?
+        }
+
+        // Reveals for the outpoints blinded by the node (in invoices or as
+        // change outputs) are found by the node itself, and payments to
+        // address invoices reveal receiver's seals in the consignment, so
+        // reveal data are required only for the externally blinded outpoints
+        let reveal_outpoints = reveals
+            .into_iter()
+            .map(OutpointReveal::from)
+            .collect::<Vec<_>>();
+        for outpoint_reveal in &reveal_outpoints {
+            if !consignment
+                .endpoints
+                .iter()
+                .any(|(_, outpoint_hash)| outpoint_reveal.conceal() == *outpoint_hash)
+            {
+                eprintln!(
+                    "The provided outpoint {}:{} and blinding factor does not match any of the consignment endpoints",
+                    outpoint_reveal.txid, outpoint_reveal.vout
+                );
                 Err(Error::DataInconsistency)?
             }
-            AcceptApi {
-                consignment,
-                reveal_outpoints: vec![outpoint_reveal],
-            }
-        } else {
-            eprintln!("Currently, this command-line tool is unable to accept consignments containing more than a single locally-controlled output point");
-            Err(Error::UnsupportedFunctionality)?
+        }
+        let witness_txs = witness
+            .into_iter()
+            .map(read_tx)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let api = AcceptApi {
+            consignment,
+            reveal_outpoints,
+            invoices,
+            witness_txs,
         };
 
         match &*runtime.accept(api)? {
@@ -323,6 +591,13 @@ impl Command {
             Reply::Success => {
                 eprintln!("Asset transfer successfully accepted.");
             }
+            Reply::ValidationStatus(status) => {
+                eprintln!(
+                    "Asset transfer was rejected since consignment is not valid ({:?}):",
+                    status.validity()
+                );
+                println!("{}", serde_yaml::to_string(&status)?);
+            }
             _ => {
                 eprintln!(
                     "Unexpected server error; probably you connecting with outdated client version"
@@ -345,6 +620,36 @@ impl Command {
             Reply::Success => {
                 eprintln!("Assets are removed from the stash.");
             }
+            Reply::Forgotten(forgotten) => {
+                eprintln!(
+                    "Assets are removed from the stash; pruned {} state transitions and {} anchors.",
+                    forgotten.transitions.len(),
+                    forgotten.anchors.len()
+                );
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exec_cancel(&self, mut runtime: Runtime, node_id: NodeId) -> Result<(), Error> {
+        info!("Cancelling pending transfer...");
+
+        match &*runtime.cancel_transfer(node_id)? {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Success => {
+                eprintln!("Transfer is cancelled; its inputs are available for spending.");
+            }
+            Reply::Nothing => {
+                eprintln!("There is no pending transfer {}", node_id);
+            }
             _ => {
                 eprintln!(
                     "Unexpected server error; probably you connecting with outdated client version"
@@ -363,107 +668,163 @@ impl Issue {
 
         let reply = runtime.issue(self)?;
         info!("Reply: {}", reply);
-        // TODO: Wait for the information from push notification
-
-        /*let (asset, genesis) = match reply {
-
-        };
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::Issue(reply::Issue { asset, genesis }) => {
+                debug!("Asset information:\n {:?}\n", asset);
+                trace!("Genesis contract:\n {:?}\n", genesis);
 
-        debug!("Asset information:\n {:?}\n", asset);
-        trace!("Genesis contract:\n {:?}\n", genesis);
+                eprintln!(
+                    "Asset {} successfully issued. Use this information for sharing:",
+                    asset.id()
+                );
+                println!("{}", genesis);
 
-        eprintln!("Asset successfully issued. Use this information for sharing:");
-        println!("{}", genesis);*/
+                // Other subscribers learn about the new asset from the push
+                // notification, so we make sure it was actually published
+                let contract_id = genesis.contract_id();
+                loop {
+                    match runtime
+                        .notification(FUNGIBLED_NOTIFICATION_TIMEOUT)?
+                        .as_deref()
+                    {
+                        Some(Notification::AssetIssued(id)) if *id == contract_id => {
+                            debug!("Node has announced asset {}", id);
+                            break;
+                        }
+                        Some(notification) => debug!("Skipping notification {}", notification),
+                        None => {
+                            eprintln!("Asset is issued, but the node has not announced it");
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
 
         Ok(())
     }
 }
 
 impl InvoiceCli {
-    pub fn exec(self, _: Runtime) -> Result<(), Error> {
+    /// Environment variable which may contain the invoice signing key
+    pub const SIGNING_KEY_ENV: &'static str = "RGB_INVOICE_SIGNING_KEY";
+
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
         info!("Generating invoice ...");
         debug!("{}", self.clone());
 
-        let outpoint_reveal = OutpointReveal::from(self.outpoint);
-        let invoice = Invoice {
-            contract_id: self.asset,
-            outpoint: Outpoint::BlindedUtxo(outpoint_reveal.conceal()),
-            amount: self.amount,
+        let signing_key = self.signing_key()?;
+
+        // Blinding data are kept by the node and used once the payment is
+        // accepted
+        let outpoint = match self.outpoint {
+            OutpointDescriptor::Utxo(outpoint) => {
+                Outpoint::BlindedUtxo(blind_outpoint(&mut runtime, outpoint)?)
+            }
+            OutpointDescriptor::Address(address) => Outpoint::Address(address),
         };
+        let mut invoice = Invoice::new(self.asset, outpoint, self.amount);
+        invoice.network = Some(runtime.network());
+        invoice.expiry = self
+            .expiry
+            .map(|expiry| {
+                Duration::from_std(std::time::Duration::from_secs(expiry))
+                    .ok()
+                    .and_then(|expiry| Utc::now().naive_utc().checked_add_signed(expiry))
+                    .ok_or(InvoiceError::WrongExpiryEncoding)
+            })
+            .transpose()?;
+        invoice.description = self.description;
+        invoice.id = Some(secp256k1::rand::thread_rng().next_u64());
+        if let Some(seckey) = signing_key {
+            invoice.sign(&seckey);
+        }
 
         eprint!("Invoice: ");
         println!("{}", invoice);
-        eprint!("Outpoint blinding factor: ");
-        println!("{}", outpoint_reveal.blinding);
+        eprint!("Compact invoice: ");
+        println!("{}", invoice.to_bech32_string()?);
 
         Ok(())
     }
+
+    /// Reads invoice signing key from the file, if provided, or from the
+    /// environment variable
+    fn signing_key(&self) -> Result<Option<secp256k1::SecretKey>, Error> {
+        let (source, hex) = if let Some(ref filename) = self.signing_key_file {
+            let source = format!("{:?}", filename);
+            let hex = fs::read_to_string(filename)
+                .map_err(|_| Error::InputFileIoError(source.clone()))?;
+            (source, hex)
+        } else if let Ok(hex) = std::env::var(Self::SIGNING_KEY_ENV) {
+            (Self::SIGNING_KEY_ENV.to_string(), hex)
+        } else {
+            return Ok(None);
+        };
+        // Parsing error details are not reported, since they may leak the key
+        hex.trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::InputFileFormatError(source, "Wrong secret key".to_string()))
+    }
 }
 
 impl TransferCli {
-    #[allow(unreachable_code)]
     pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
         info!("Transferring asset ...");
         debug!("{}", self.clone());
 
-        let seal_confidential = match self.invoice.outpoint {
-            Outpoint::BlindedUtxo(outpoint_hash) => outpoint_hash,
-            Outpoint::Address(_address) => {
-                // To do a pay-to-address, we need to spend some bitcoins,
-                // which we have to take from somewhere. While payee can
-                // provide us with additional input, it's not part of the
-                // invoicing protocol + does not make a lot of sense, since
-                // the same input can be simply used by Utxo scheme
-                unimplemented!();
-                SealDefinition::WitnessVout {
-                    vout: 0,
-                    blinding: 0,
-                }
-                .conceal()
-            }
-        };
+        self.invoice
+            .validate(&runtime.network(), self.receiver_pubkey.as_ref())?;
 
-        let pubkey_key = Key {
-            type_value: 0xFC,
-            key: PSBT_PUBKEY_KEY.to_vec(),
-        };
-        let fee_key = Key {
-            type_value: 0xFC,
-            key: PSBT_FEE_KEY.to_vec(),
+        let change = match (self.change, self.change_blinded) {
+            (Some(outpoint), _) => Some(blind_outpoint(&mut runtime, outpoint)?),
+            (None, change_blinded) => change_blinded,
         };
 
-        debug!(
-            "Reading partially-signed transaction from file {:?}",
-            self.prototype
-        );
-        let filepath = format!("{:?}", &self.prototype);
-        let file = fs::File::open(self.prototype)
-            .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
-        let mut psbt = PartiallySignedTransaction::consensus_decode(file).map_err(|err| {
-            Error::InputFileFormatError(format!("{:?}", filepath), format!("{}", err))
-        })?;
-
-        psbt.global
-            .unknown
-            .insert(fee_key, self.fee.to_be_bytes().to_vec());
-        for output in &mut psbt.outputs {
-            output.unknown.insert(
-                pubkey_key.clone(),
-                output.hd_keypaths.keys().next().unwrap().to_bytes(),
-            );
-        }
-        trace!("{:?}", psbt);
+        let mut psbt = read_psbt(self.prototype, self.fee)?;
+
+        let (theirs, theirs_witness) = match self.invoice.outpoint {
+            Outpoint::BlindedUtxo(seal_confidential) => (
+                vec![Outcoincealed {
+                    coins: self.invoice.amount,
+                    seal_confidential,
+                }],
+                vec![],
+            ),
+            Outpoint::Address(address) => {
+                // Receiver has no UTXO yet, so we create a new output paying
+                // to the invoice address and assign the assets to it
+                let vout = add_address_output(&mut psbt, &address, self.sats)?;
+                (
+                    vec![],
+                    vec![Outcoins {
+                        coins: self.invoice.amount,
+                        vout,
+                        txid: None,
+                    }],
+                )
+            }
+        };
 
         let api = TransferApi {
+            network: runtime.network(),
             psbt,
             contract_id: self.invoice.contract_id,
             inputs: self.inputs,
+            selection: self.selection,
             ours: self.allocate,
-            theirs: vec![Outcoincealed {
-                coins: self.invoice.amount,
-                seal_confidential,
-            }],
-            change: None, // TODO
+            theirs,
+            theirs_witness,
+            change,
         };
 
         // TODO: Do tx output reorg for deterministic ordering
@@ -490,3 +851,165 @@ impl TransferCli {
         Ok(())
     }
 }
+
+impl InflateCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Inflating asset ...");
+        debug!("{}", self.clone());
+
+        let api = InflateApi {
+            contract_id: self.asset,
+            network: runtime.network(),
+            psbt: read_psbt(self.prototype, self.fee)?,
+            allocate: self.allocate,
+            next_issue: self.next_issue,
+        };
+
+        let reply = runtime.inflate(api)?;
+        info!("Reply: {}", reply);
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Inflation failed: {}", failure);
+            }
+            Reply::Transfer(transfer) => {
+                transfer.consignment.write_file(self.consignment.clone())?;
+                let out_file = fs::File::create(&self.transaction)
+                    .expect("can't create output transaction file");
+                transfer.psbt.consensus_encode(out_file)?;
+                println!(
+                    "Inflation succeeded, consignment data are written to {:?}, partially signed witness transaction to {:?}",
+                    self.consignment, self.transaction
+                );
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+impl PruneCli {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        info!("Pruning asset ...");
+        debug!("{}", self.clone());
+
+        let api = PruneApi {
+            contract_id: self.asset,
+            network: runtime.network(),
+            psbt: read_psbt(self.prototype, self.fee)?,
+            prune_right: self.prune_right,
+            inputs: self.inputs,
+            remainder: self.allocate,
+            next_prune: self.next_prune,
+            proof: self
+                .proof
+                .as_ref()
+                .map(|proof| Vec::<u8>::from_hex(proof))
+                .transpose()?,
+        };
+
+        let reply = runtime.prune(api)?;
+        info!("Reply: {}", reply);
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Pruning failed: {}", failure);
+            }
+            Reply::Transfer(transfer) => {
+                transfer.consignment.write_file(self.consignment.clone())?;
+                let out_file = fs::File::create(&self.transaction)
+                    .expect("can't create output transaction file");
+                transfer.psbt.consensus_encode(out_file)?;
+                println!(
+                    "Pruning succeeded, consignment data are written to {:?}, partially signed witness transaction to {:?}",
+                    self.consignment, self.transaction
+                );
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads consensus-encoded bitcoin transaction from a file
+fn read_tx(filename: PathBuf) -> Result<bitcoin::Transaction, Error> {
+    debug!("Reading transaction from file {:?}", filename);
+    let filepath = format!("{:?}", &filename);
+    let file = fs::File::open(filename).map_err(|_| Error::InputFileIoError(filepath.clone()))?;
+    bitcoin::Transaction::consensus_decode(file)
+        .map_err(|err| Error::InputFileFormatError(filepath, format!("{}", err)))
+}
+
+/// Reads partially-signed transaction prototype from a file and adds the
+/// fee and output public key information required for RGB commitments
+fn read_psbt(prototype: PathBuf, fee: u64) -> Result<PartiallySignedTransaction, Error> {
+    let pubkey_key = Key {
+        type_value: 0xFC,
+        key: PSBT_PUBKEY_KEY.to_vec(),
+    };
+    let fee_key = Key {
+        type_value: 0xFC,
+        key: PSBT_FEE_KEY.to_vec(),
+    };
+
+    debug!(
+        "Reading partially-signed transaction from file {:?}",
+        prototype
+    );
+    let filepath = format!("{:?}", &prototype);
+    let file = fs::File::open(prototype)
+        .map_err(|_| Error::InputFileIoError(format!("{:?}", filepath)))?;
+    let mut psbt = PartiallySignedTransaction::consensus_decode(file).map_err(|err| {
+        Error::InputFileFormatError(format!("{:?}", filepath), format!("{}", err))
+    })?;
+
+    psbt.global
+        .unknown
+        .insert(fee_key, fee.to_be_bytes().to_vec());
+    for output in &mut psbt.outputs {
+        output.unknown.insert(
+            pubkey_key.clone(),
+            output.hd_keypaths.keys().next().unwrap().to_bytes(),
+        );
+    }
+    trace!("{:?}", psbt);
+
+    Ok(psbt)
+}
+
+/// Adds output paying given amount of satoshis to the address to the
+/// partially-signed transaction; returns number of the new output
+fn add_address_output(
+    psbt: &mut PartiallySignedTransaction,
+    address: &bitcoin::Address,
+    sats: u64,
+) -> Result<u16, Error> {
+    let vout = psbt.global.unsigned_tx.output.len();
+    if vout > core::u16::MAX as usize {
+        Err(Error::UnsupportedFunctionality)?
+    }
+    psbt.global.unsigned_tx.output.push(bitcoin::TxOut {
+        value: sats,
+        script_pubkey: address.script_pubkey(),
+    });
+    psbt.outputs.push(Default::default());
+    Ok(vout as u16)
+}
+
+/// Asks the node to blind the outpoint; blinding data are kept by the node
+/// and used to accept incoming transfers
+fn blind_outpoint(runtime: &mut Runtime, outpoint: OutPoint) -> Result<OutpointHash, Error> {
+    match &*runtime.blind(outpoint)? {
+        Reply::BlindedOutpoint(outpoint_hash) => Ok(*outpoint_hash),
+        Reply::Failure(failure) => {
+            eprintln!("Server returned error: {}", failure);
+            Err(Error::DataInconsistency)
+        }
+        _ => {
+            eprintln!(
+                "Unexpected server error; probably you connecting with outdated client version"
+            );
+            Err(Error::UnsupportedFunctionality)
+        }
+    }
+}
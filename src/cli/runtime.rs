@@ -14,20 +14,26 @@
 use std::sync::Arc;
 
 use lnpbp::bitcoin::OutPoint;
+use lnpbp::bp;
 use lnpbp::lnp::presentation::Encode;
 use lnpbp::lnp::transport::zmq::ApiType;
 use lnpbp::lnp::{transport, NoEncryption, Session, Unmarshall, Unmarshaller};
-use lnpbp::rgb::{Consignment, ContractId, Genesis};
+use lnpbp::rgb::{Consignment, ContractId, Genesis, NodeId, SchemaId};
 
 use super::{Config, Error};
-use crate::api::fungible::{AcceptApi, Issue, Request, TransferApi};
-use crate::api::Reply;
+use crate::api::fungible::{
+    AcceptApi, InflateApi, Issue, Notification, PruneApi, Request, TransferApi,
+};
+use crate::api::{self, Reply};
 use crate::error::{BootstrapError, ServiceErrorDomain};
 
 pub struct Runtime {
     config: Config,
     session_rpc: Session<NoEncryption, transport::zmq::Connection>,
+    session_sub: Session<NoEncryption, transport::zmq::Connection>,
+    stash_rpc: Session<NoEncryption, transport::zmq::Connection>,
     unmarshaller: Unmarshaller<Reply>,
+    notification_unmarshaller: Unmarshaller<Notification>,
 }
 
 impl Runtime {
@@ -39,10 +45,27 @@ impl Runtime {
             config.endpoint.clone(),
             None,
         )?;
+        // We subscribe before sending any requests, so the notifications
+        // caused by them are not missed
+        let session_sub = Session::new_zmq_unencrypted(
+            ApiType::Subscribe,
+            &mut context,
+            config.pub_endpoint.clone(),
+            None,
+        )?;
+        let stash_rpc = Session::new_zmq_unencrypted(
+            ApiType::Client,
+            &mut context,
+            config.stash_endpoint.clone(),
+            None,
+        )?;
         Ok(Self {
             config,
             session_rpc,
+            session_sub,
+            stash_rpc,
             unmarshaller: Reply::create_unmarshaller(),
+            notification_unmarshaller: Notification::create_unmarshaller(),
         })
     }
 
@@ -54,6 +77,42 @@ impl Runtime {
         Ok(reply)
     }
 
+    fn stash_command(
+        &mut self,
+        command: api::stash::Request,
+    ) -> Result<Arc<Reply>, ServiceErrorDomain> {
+        let data = command.encode()?;
+        self.stash_rpc.send_raw_message(data)?;
+        let raw = self.stash_rpc.recv_raw_message()?;
+        let reply = self.unmarshaller.unmarshall(&raw)?;
+        Ok(reply)
+    }
+
+    /// Waits for the next notification from fungibled for up to `timeout`
+    /// milliseconds; returns `None` if nothing has arrived
+    pub fn notification(&mut self, timeout: i64) -> Result<Option<Arc<Notification>>, Error> {
+        let mut poll_items = [self.session_sub.as_socket().as_poll_item(zmq::POLLIN)];
+        zmq::poll(&mut poll_items, timeout)
+            .map_err(|err| ServiceErrorDomain::Internal(err.to_string()))?;
+        if !poll_items[0].is_readable() {
+            return Ok(None);
+        }
+        let raw = self
+            .session_sub
+            .recv_raw_message()
+            .map_err(ServiceErrorDomain::from)?;
+        let notification = self
+            .notification_unmarshaller
+            .unmarshall(&raw)
+            .map_err(ServiceErrorDomain::from)?;
+        Ok(Some(notification))
+    }
+
+    #[inline]
+    pub fn network(&self) -> bp::Network {
+        self.config.network.clone()
+    }
+
     #[inline]
     pub fn list(&mut self) -> Result<Arc<Reply>, Error> {
         Ok(self.command(Request::Sync)?)
@@ -79,6 +138,16 @@ impl Runtime {
         Ok(self.command(Request::Transfer(transfer))?)
     }
 
+    #[inline]
+    pub fn inflate(&mut self, inflate: InflateApi) -> Result<Arc<Reply>, Error> {
+        Ok(self.command(Request::Inflate(inflate))?)
+    }
+
+    #[inline]
+    pub fn prune(&mut self, prune: PruneApi) -> Result<Arc<Reply>, Error> {
+        Ok(self.command(Request::Prune(prune))?)
+    }
+
     #[inline]
     pub fn validate(&mut self, consignment: Consignment) -> Result<Arc<Reply>, Error> {
         Ok(self.command(Request::Validate(consignment))?)
@@ -89,8 +158,53 @@ impl Runtime {
         Ok(self.command(Request::Accept(accept))?)
     }
 
+    #[inline]
+    pub fn blind(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
+        Ok(self.command(Request::Blind(outpoint))?)
+    }
+
+    #[inline]
+    pub fn balance(&mut self, contract_id: ContractId) -> Result<Arc<Reply>, Error> {
+        Ok(self.command(Request::Balance(contract_id))?)
+    }
+
+    #[inline]
+    pub fn history(&mut self, contract_id: ContractId) -> Result<Arc<Reply>, Error> {
+        Ok(self.command(Request::History(contract_id))?)
+    }
+
+    #[inline]
+    pub fn cancel_transfer(&mut self, node_id: NodeId) -> Result<Arc<Reply>, Error> {
+        Ok(self.command(Request::CancelTransfer(node_id))?)
+    }
+
     #[inline]
     pub fn forget(&mut self, outpoint: OutPoint) -> Result<Arc<Reply>, Error> {
         Ok(self.command(Request::Forget(outpoint))?)
     }
+
+    #[inline]
+    pub fn list_schemata(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(api::stash::Request::ListSchemata())?)
+    }
+
+    #[inline]
+    pub fn read_schemata(&mut self, ids: Vec<SchemaId>) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(api::stash::Request::ReadSchemata(ids))?)
+    }
+
+    #[inline]
+    pub fn list_geneses(&mut self) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(api::stash::Request::ListGeneses())?)
+    }
+
+    #[inline]
+    pub fn read_genesis(&mut self, contract_id: ContractId) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(api::stash::Request::ReadGenesis(contract_id))?)
+    }
+
+    #[inline]
+    pub fn read_transitions(&mut self, ids: Vec<NodeId>) -> Result<Arc<Reply>, Error> {
+        Ok(self.stash_command(api::stash::Request::ReadTransitions(ids))?)
+    }
 }
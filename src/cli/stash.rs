@@ -0,0 +1,82 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use clap::Clap;
+
+use lnpbp::rgb::prelude::*;
+
+use super::{Error, Runtime};
+use crate::api::Reply;
+
+#[derive(Clap, Clone, Debug, Display)]
+#[display_from(Debug)]
+pub enum Command {
+    /// Lists ids of all schemata known to the stash
+    Schemata,
+
+    /// Reads schemata with given ids from the stash
+    Schema {
+        /// Schema ids
+        #[clap(required = true)]
+        ids: Vec<SchemaId>,
+    },
+
+    /// Lists ids of all contracts (geneses) known to the stash
+    Geneses,
+
+    /// Reads contract genesis from the stash
+    Genesis {
+        /// Bech32 representation of the contract id
+        contract_id: ContractId,
+    },
+
+    /// Reads state transitions with given ids from the stash
+    Transitions {
+        /// State transition ids
+        #[clap(required = true)]
+        ids: Vec<NodeId>,
+    },
+}
+
+impl Command {
+    pub fn exec(self, mut runtime: Runtime) -> Result<(), Error> {
+        let reply = match self {
+            Command::Schemata => runtime.list_schemata()?,
+            Command::Schema { ids } => runtime.read_schemata(ids)?,
+            Command::Geneses => runtime.list_geneses()?,
+            Command::Genesis { contract_id } => runtime.read_genesis(contract_id)?,
+            Command::Transitions { ids } => runtime.read_transitions(ids)?,
+        };
+
+        match &*reply {
+            Reply::Failure(failure) => {
+                eprintln!("Server returned error: {}", failure);
+            }
+            Reply::SchemaIds(ids) => ids.iter().for_each(|id| println!("{}", id)),
+            Reply::Schemata(schemata) => schemata.iter().for_each(|schema| println!("{}", schema)),
+            Reply::ContractIds(ids) => ids
+                .iter()
+                .for_each(|id| println!("{}", id.to_bech32_string())),
+            Reply::Genesis(genesis) => println!("{}", genesis),
+            Reply::Transitions(transitions) => transitions
+                .iter()
+                .for_each(|transition| println!("{}", transition)),
+            _ => {
+                eprintln!(
+                    "Unexpected server error; probably you connecting with outdated client version"
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -14,6 +14,8 @@
 //! Shared constants, including configuration parameters etc
 
 pub const RGB20_BECH32_HRP_INVOICE: &'static str = "rgb20:";
+pub const RGB20_BECH32_HRP_INVOICE_COMPACT: &'static str = "rgbi";
+pub const RGB20_ADDRESS_OUTPUT_SATS: &'static str = "1000";
 
 pub const RGB_DATA_DIR: &'static str = "/var/lib/rgb";
 pub const RGB_BIN_DIR: &'static str = "/usr/local/bin";
@@ -27,5 +29,8 @@ pub const STASHD_RPC_ENDPOINT: &'static str = "ipc:{data_dir}/{network}/stashd.r
 pub const STASHD_PUB_ENDPOINT: &'static str = "ipc:{data_dir}/{network}/stashd.pub";
 
 pub const FUNGIBLED_CACHE: &'static str = "{data_dir}/{network}/cache/fungible";
+pub const FUNGIBLED_INVOICES: &'static str = "{data_dir}/{network}/invoices/fungible";
 pub const FUNGIBLED_RPC_ENDPOINT: &'static str = "ipc:{data_dir}/{network}/fungibled.rpc";
 pub const FUNGIBLED_PUB_ENDPOINT: &'static str = "ipc:{data_dir}/{network}/fungibled.pub";
+/// Time (in milliseconds) for clients waiting for fungibled notifications
+pub const FUNGIBLED_NOTIFICATION_TIMEOUT: i64 = 5000;
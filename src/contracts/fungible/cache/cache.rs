@@ -13,7 +13,7 @@
 
 use lnpbp::rgb::prelude::*;
 
-use super::FileCacheError;
+use super::{FileCacheError, SqlCacheError};
 use crate::error::{BootstrapError, ServiceErrorDomain};
 use crate::fungible::Asset;
 use crate::util::file::FileMode;
@@ -26,6 +26,9 @@ pub trait Cache {
     fn has_asset(&self, id: ContractId) -> Result<bool, Self::Error>;
     fn add_asset(&mut self, asset: Asset) -> Result<bool, Self::Error>;
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, Self::Error>;
+
+    /// Exports all known assets in the data format configured for the cache
+    fn export(&self) -> Result<Vec<u8>, Self::Error>;
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
@@ -77,3 +80,28 @@ impl From<FileCacheError> for CacheError {
         }
     }
 }
+
+impl From<SqlCacheError> for CacheError {
+    fn from(err: SqlCacheError) -> Self {
+        match err {
+            SqlCacheError::Io(e) => Self::Io(format!("{:?}", e)),
+            SqlCacheError::Connection(e) => Self::Io(format!("{:?}", e)),
+            SqlCacheError::Query(e) => Self::Io(format!("{:?}", e)),
+            SqlCacheError::Migration(e) => Self::Io(format!("{:?}", e)),
+            SqlCacheError::BrokenHexData => {
+                Self::DataIntegrityError("Broken hex data in cache database".to_string())
+            }
+            SqlCacheError::Encoding(e) => Self::DataIntegrityError(format!("{:?}", e)),
+            SqlCacheError::SerdeJson(e) => Self::DataIntegrityError(format!("{:?}", e)),
+            SqlCacheError::SerdeYaml(e) => Self::DataIntegrityError(format!("{:?}", e)),
+            SqlCacheError::SerdeToml => {
+                Self::DataIntegrityError(format!("TOML serialization/deserialization error"))
+            }
+            SqlCacheError::UnsupportedBackend(connection) => Self::Io(format!(
+                "Database backend for {} is not supported by this build",
+                connection
+            )),
+            SqlCacheError::DataIntegrity(details) => Self::DataIntegrityError(details),
+        }
+    }
+}
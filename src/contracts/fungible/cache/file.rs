@@ -11,6 +11,7 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use serde::{Deserialize, Serialize};
 use serde_json;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -19,6 +20,7 @@ use std::{fs, io, io::Read, io::Write};
 use lnpbp::bitcoin;
 use lnpbp::data_format::DataFormat;
 use lnpbp::rgb::prelude::*;
+use lnpbp::strict_encoding::{strict_decode, strict_encode};
 
 use super::Cache;
 use crate::error::{BootstrapError, ServiceErrorDomain};
@@ -52,6 +54,10 @@ pub enum FileCacheError {
 
     #[derive_from(std::option::NoneError)]
     NotFound,
+
+    /// Journal contains a complete record which can't be parsed; the number
+    /// is the index of the broken record
+    CorruptedJournal(usize),
 }
 
 impl From<FileCacheError> for ServiceErrorDomain {
@@ -71,9 +77,16 @@ impl From<FileCacheError> for BootstrapError {
 pub struct FileCacheConfig {
     pub data_dir: PathBuf,
     pub data_format: DataFormat,
+    /// Whether cache updates should be appended to the journal instead of
+    /// rewriting the whole assets file on each change
+    pub journal: bool,
 }
 
 impl FileCacheConfig {
+    /// Number of journal records after which the journal is compacted and
+    /// the assets file is rewritten
+    pub const JOURNAL_COMPACTION_THRESHOLD: usize = 256;
+
     #[inline]
     pub fn assets_dir(&self) -> PathBuf {
         self.data_dir.clone()
@@ -85,6 +98,24 @@ impl FileCacheConfig {
             .join("assets")
             .with_extension(self.data_format.extension())
     }
+
+    #[inline]
+    pub fn journal_filename(&self) -> PathBuf {
+        self.assets_dir().join("assets").with_extension("journal")
+    }
+}
+
+/// Line terminating each of the journal records
+const JOURNAL_RECORD_END: &'static str = "...";
+
+/// Single cache update stored in the journal; the journal is a stream of YAML
+/// documents, one per record, each terminated with an explicit document end
+/// marker, so that a partially written record can be detected
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Display)]
+#[display_from(Debug)]
+enum JournalRecord {
+    Add(Asset),
+    Remove(ContractId),
 }
 
 /// Keeps all source/binary RGB contract data, stash etc
@@ -93,6 +124,8 @@ impl FileCacheConfig {
 pub struct FileCache {
     config: FileCacheConfig,
     assets: HashMap<ContractId, Asset>,
+    /// Number of records appended to the journal since its last compaction
+    journal_len: usize,
 }
 
 impl FileCache {
@@ -119,16 +152,152 @@ impl FileCache {
         let mut me = Self {
             config,
             assets: map![],
+            journal_len: 0,
         };
-        let filename = me.config.assets_filename();
+        me.recover()?;
+
+        Ok(me)
+    }
+
+    /// Restores cache state after a normal shutdown as well as after a crash:
+    /// reads assets file (if it is present and not corrupted) and replays all
+    /// journal records on top of it. Since compacted journal contains the
+    /// full cache state, the data can be recovered from the journal even if
+    /// the assets file is missing or broken.
+    fn recover(&mut self) -> Result<(), FileCacheError> {
+        let filename = self.config.assets_filename();
+        let journal_filename = self.config.journal_filename();
+
+        // Leftovers from interrupted atomic writes are never valid
+        for leftover in &[tmp_filename(&filename), tmp_filename(&journal_filename)] {
+            if leftover.exists() {
+                warn!("Removing incomplete cache file {:?}", leftover);
+                fs::remove_file(leftover)?;
+            }
+        }
+
+        let mut rewrite = false;
         if filename.exists() {
-            me.load()?;
+            if let Err(err) = self.load() {
+                if !journal_filename.exists() {
+                    error!(
+                        "Assets file {:?} is corrupted and there is no journal to recover from",
+                        filename
+                    );
+                    Err(err)?
+                }
+                let corrupted = filename.with_extension("corrupted");
+                warn!(
+                    "Assets file {:?} is corrupted ({}); moving it to {:?} and recovering from journal",
+                    filename, err, corrupted
+                );
+                fs::rename(&filename, corrupted)?;
+                self.assets = map![];
+                rewrite = true;
+            }
         } else {
             debug!("Initializing assets file {:?} ...", filename.to_str());
-            me.save()?;
+            rewrite = true;
         }
 
-        Ok(me)
+        if journal_filename.exists() {
+            rewrite |= self.replay_journal()? || !self.config.journal;
+        } else {
+            // Journal must always start with the full cache state
+            rewrite |= self.config.journal;
+        }
+
+        if rewrite {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Applies all records from the journal to the in-memory cache; returns
+    /// whether the journal has to be compacted (i.e. some records were
+    /// applied or an incomplete record was dropped). A record which is not
+    /// terminated may appear only at the end of the journal (as a result of a
+    /// crash during append); it is ignored. Any complete record which can't
+    /// be parsed means the journal is corrupted, and results in an error
+    /// since the cache state can't be reliably restored.
+    fn replay_journal(&mut self) -> Result<bool, FileCacheError> {
+        debug!("Replaying assets journal ...");
+        let mut data = String::new();
+        file(self.config.journal_filename(), FileMode::Read)?.read_to_string(&mut data)?;
+
+        let mut count = 0usize;
+        let mut record = String::new();
+        for line in data.lines() {
+            if line != JOURNAL_RECORD_END {
+                record.push_str(line);
+                record.push('\n');
+                continue;
+            }
+            match serde_yaml::from_str::<JournalRecord>(&record) {
+                Ok(JournalRecord::Add(asset)) => {
+                    self.assets.insert(*asset.id(), asset);
+                }
+                Ok(JournalRecord::Remove(id)) => {
+                    self.assets.remove(&id);
+                }
+                Err(err) => {
+                    error!(
+                        "Assets journal {:?} is corrupted at record {} ({})",
+                        self.config.journal_filename(),
+                        count,
+                        err
+                    );
+                    Err(FileCacheError::CorruptedJournal(count))?
+                }
+            }
+            record.clear();
+            count += 1;
+        }
+        let truncated = !record.trim().is_empty();
+        if truncated {
+            warn!(
+                "Assets journal is truncated after {} records; ignoring incomplete record",
+                count
+            );
+        }
+        Ok(count > 0 || truncated)
+    }
+
+    /// Appends a record to the journal, compacting it when it grows too large
+    fn append_journal(&mut self, record: JournalRecord) -> Result<(), FileCacheError> {
+        trace!("Appending assets journal ...");
+        let mut f = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.config.journal_filename())?;
+        f.write_all(&journal_entry(&record)?)?;
+        f.sync_data()?;
+
+        self.journal_len += 1;
+        if self.journal_len >= FileCacheConfig::JOURNAL_COMPACTION_THRESHOLD {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites assets file with the current cache state. If the journal is
+    /// enabled, replaces it with a compacted version holding a single record
+    /// per known asset; otherwise removes the journal.
+    fn compact(&mut self) -> Result<(), FileCacheError> {
+        self.save()?;
+        let journal_filename = self.config.journal_filename();
+        if self.config.journal {
+            debug!("Compacting assets journal ...");
+            let mut data = vec![];
+            for asset in self.assets.values() {
+                data.extend(journal_entry(&JournalRecord::Add(asset.clone()))?);
+            }
+            write_atomically(&journal_filename, &data)?;
+        } else if journal_filename.exists() {
+            fs::remove_file(journal_filename)?;
+        }
+        self.journal_len = 0;
+        Ok(())
     }
 
     fn load(&mut self) -> Result<(), FileCacheError> {
@@ -143,25 +312,43 @@ impl FileCache {
                 f.read_to_string(&mut data)?;
                 toml::from_str(&data)?
             }
-            DataFormat::StrictEncode => unimplemented!(),
+            DataFormat::StrictEncode => {
+                let mut data = vec![];
+                f.read_to_end(&mut data)?;
+                strict_decode::<Vec<Asset>>(&data)?
+                    .into_iter()
+                    .map(|asset| (*asset.id(), asset))
+                    .collect()
+            }
         };
         Ok(())
     }
 
+    /// Writes the full cache state into the assets file. The data are written
+    /// to a temporary file first, which then atomically replaces the original
+    /// one, so a crash never leaves the assets file half-written.
     pub fn save(&self) -> Result<(), FileCacheError> {
         trace!("Saving assets information ...");
-        let filename = self.config.assets_filename();
-        let _ = fs::remove_file(&filename);
-        let mut f = file(filename, FileMode::Create)?;
-        match self.config.data_format {
-            DataFormat::Yaml => serde_yaml::to_writer(&f, &self.assets)?,
-            DataFormat::Json => serde_json::to_writer(&f, &self.assets)?,
-            DataFormat::Toml => f.write_all(&toml::to_vec(&self.assets)?)?,
-            DataFormat::StrictEncode => unimplemented!(),
-        }
+        let data = match self.config.data_format {
+            DataFormat::Yaml => serde_yaml::to_vec(&self.assets)?,
+            DataFormat::Json => serde_json::to_vec(&self.assets)?,
+            DataFormat::Toml => toml::to_vec(&self.assets)?,
+            DataFormat::StrictEncode => {
+                strict_encode(&self.assets.values().cloned().collect::<Vec<Asset>>())?
+            }
+        };
+        write_atomically(&self.config.assets_filename(), &data)?;
         Ok(())
     }
 
+    fn update(&mut self, record: JournalRecord) -> Result<(), FileCacheError> {
+        if self.config.journal {
+            self.append_journal(record)
+        } else {
+            self.save()
+        }
+    }
+
     pub fn export(&self) -> Result<Vec<u8>, FileCacheError> {
         trace!("Exporting assets information ...");
         let assets = self.assets.values().collect::<Vec<&Asset>>();
@@ -169,7 +356,9 @@ impl FileCache {
             DataFormat::Yaml => serde_yaml::to_vec(&assets)?,
             DataFormat::Json => serde_json::to_vec(&assets)?,
             DataFormat::Toml => toml::to_vec(&assets)?,
-            DataFormat::StrictEncode => unimplemented!(),
+            DataFormat::StrictEncode => {
+                strict_encode(&assets.into_iter().cloned().collect::<Vec<Asset>>())?
+            }
         })
     }
 }
@@ -194,15 +383,169 @@ impl Cache for FileCache {
     }
 
     fn add_asset(&mut self, asset: Asset) -> Result<bool, CacheError> {
-        let exists = self.assets.insert(*asset.id(), asset).is_some();
-        self.save()?;
+        let exists = self.assets.insert(*asset.id(), asset.clone()).is_some();
+        self.update(JournalRecord::Add(asset))?;
         Ok(exists)
     }
 
     #[inline]
     fn remove_asset(&mut self, id: ContractId) -> Result<bool, CacheError> {
         let existed = self.assets.remove(&id).is_some();
-        self.save()?;
+        if existed {
+            self.update(JournalRecord::Remove(id))?;
+        }
         Ok(existed)
     }
+
+    #[inline]
+    fn export(&self) -> Result<Vec<u8>, CacheError> {
+        Ok(FileCache::export(self)?)
+    }
+}
+
+fn journal_entry(record: &JournalRecord) -> Result<Vec<u8>, FileCacheError> {
+    let mut entry = serde_yaml::to_string(record)?;
+    if !entry.ends_with('\n') {
+        entry.push('\n');
+    }
+    entry.push_str(JOURNAL_RECORD_END);
+    entry.push('\n');
+    Ok(entry.into_bytes())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use lnpbp::bitcoin::hashes::Hash;
+    use lnpbp::bp;
+
+    use crate::fungible::{Coins, Supply};
+
+    fn config(name: &str) -> FileCacheConfig {
+        let data_dir = std::env::temp_dir().join(format!(
+            "rgb-file-cache-test-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&data_dir);
+        FileCacheConfig {
+            data_dir,
+            data_format: DataFormat::Yaml,
+            journal: true,
+        }
+    }
+
+    fn asset(no: u8) -> Asset {
+        Asset::with(
+            ContractId::from_inner([no; 32]),
+            format!("TST{}", no),
+            format!("Test asset {}", no),
+            None,
+            Supply {
+                known_circulating: Coins::with_sats_precision(no as u64 * 1000, 8),
+                total: None,
+            },
+            Coins::with_sats_precision(0, 8),
+            bp::Network::from(bitcoin::Network::Testnet),
+            8,
+            NaiveDateTime::from_timestamp(1_593_561_600, 0),
+            None,
+            vec![],
+            bmap! {},
+            bmap! {},
+            vec![],
+        )
+    }
+
+    fn append(filename: PathBuf, data: &[u8]) {
+        fs::OpenOptions::new()
+            .append(true)
+            .open(filename)
+            .unwrap()
+            .write_all(data)
+            .unwrap();
+    }
+
+    #[test]
+    fn leftover_tmp_files_removed() {
+        let config = config("leftovers");
+        fs::create_dir_all(&config.data_dir).unwrap();
+        let leftovers = [
+            tmp_filename(&config.assets_filename()),
+            tmp_filename(&config.journal_filename()),
+        ];
+        for leftover in &leftovers {
+            fs::write(leftover, b"incomplete").unwrap();
+        }
+
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert!(cache.assets().unwrap().is_empty());
+        assert!(leftovers.iter().all(|leftover| !leftover.exists()));
+
+        let _ = fs::remove_dir_all(config.data_dir);
+    }
+
+    #[test]
+    fn journal_replayed() {
+        let config = config("replay");
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        cache.add_asset(asset(1)).unwrap();
+        cache.add_asset(asset(2)).unwrap();
+        cache.remove_asset(*asset(1).id()).unwrap();
+        drop(cache);
+
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert!(!cache.has_asset(*asset(1).id()).unwrap());
+        assert_eq!(cache.asset(*asset(2).id()).unwrap(), &asset(2));
+
+        let _ = fs::remove_dir_all(config.data_dir);
+    }
+
+    #[test]
+    fn truncated_journal_tail_ignored() {
+        let config = config("truncated");
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        cache.add_asset(asset(1)).unwrap();
+        drop(cache);
+
+        // Crash in the middle of appending the next record
+        let entry = journal_entry(&JournalRecord::Add(asset(2))).unwrap();
+        append(config.journal_filename(), &entry[..entry.len() / 2]);
+
+        let cache = FileCache::new(config.clone()).unwrap();
+        assert_eq!(cache.asset(*asset(1).id()).unwrap(), &asset(1));
+        assert!(!cache.has_asset(*asset(2).id()).unwrap());
+        drop(cache);
+
+        // Incomplete record must be dropped by the journal compaction
+        let journal = fs::read(config.journal_filename()).unwrap();
+        assert_eq!(
+            journal,
+            journal_entry(&JournalRecord::Add(asset(1))).unwrap()
+        );
+
+        let _ = fs::remove_dir_all(config.data_dir);
+    }
+
+    #[test]
+    fn corrupted_journal_record() {
+        let config = config("corrupted");
+        let mut cache = FileCache::new(config.clone()).unwrap();
+        cache.add_asset(asset(1)).unwrap();
+        cache.add_asset(asset(2)).unwrap();
+        drop(cache);
+
+        append(
+            config.journal_filename(),
+            format!("Add: [broken\n{}\n", JOURNAL_RECORD_END).as_bytes(),
+        );
+
+        match FileCache::new(config.clone()) {
+            Err(FileCacheError::CorruptedJournal(index)) => assert_eq!(index, 2),
+            result => panic!("Corrupted journal is not detected: {:?}", result),
+        }
+
+        let _ = fs::remove_dir_all(config.data_dir);
+    }
 }
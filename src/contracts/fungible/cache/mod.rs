@@ -13,6 +13,8 @@
 
 mod cache;
 mod file;
+mod sql;
 
 pub use cache::{Cache, CacheError};
 pub use file::{FileCache, FileCacheConfig, FileCacheError};
+pub use sql::{SqlCache, SqlCacheConfig, SqlCacheError};
@@ -10,3 +10,813 @@
 // You should have received a copy of the MIT License
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
+
+use core::str::FromStr;
+use std::collections::{BTreeMap, HashMap, LinkedList};
+use std::path::PathBuf;
+use std::{fs, io};
+
+use diesel::prelude::*;
+#[cfg(feature = "postgres")]
+use diesel::PgConnection;
+use diesel::SqliteConnection;
+
+use lnpbp::bitcoin;
+use lnpbp::bitcoin::hashes::hex::{FromHex, ToHex};
+use lnpbp::bitcoin::{OutPoint, Txid};
+use lnpbp::bp;
+use lnpbp::bp::blind::OutpointHash;
+use lnpbp::data_format::DataFormat;
+use lnpbp::rgb::prelude::*;
+use lnpbp::strict_encoding::{strict_encode, StrictDecode};
+
+use super::Cache;
+use crate::db::models::{AllocationRow, AssetRow, IssueRow, LedgerRow, PruneRightRow};
+use crate::db::schema::{allocations, assets, issues, ledger, prune_rights};
+use crate::error::{BootstrapError, ServiceErrorDomain};
+use crate::fungible::cache::CacheError;
+use crate::fungible::{Allocation, Asset, Coins, Issue, LedgerEntry, PruneRight, Supply};
+
+embed_migrations!("migrations");
+
+#[derive(Debug, Display, Error, From)]
+#[display_from(Debug)]
+pub enum SqlCacheError {
+    #[derive_from]
+    Io(io::Error),
+
+    #[derive_from]
+    Connection(diesel::ConnectionError),
+
+    #[derive_from]
+    Query(diesel::result::Error),
+
+    #[derive_from]
+    Migration(diesel_migrations::RunMigrationsError),
+
+    #[derive_from(bitcoin::hashes::hex::Error)]
+    BrokenHexData,
+
+    #[derive_from]
+    Encoding(lnpbp::strict_encoding::Error),
+
+    #[derive_from]
+    SerdeJson(serde_json::Error),
+
+    #[derive_from]
+    SerdeYaml(serde_yaml::Error),
+
+    #[derive_from(toml::ser::Error)]
+    SerdeToml,
+
+    UnsupportedBackend(String),
+
+    DataIntegrity(String),
+}
+
+impl From<SqlCacheError> for ServiceErrorDomain {
+    fn from(_: SqlCacheError) -> Self {
+        ServiceErrorDomain::Cache
+    }
+}
+
+impl From<SqlCacheError> for BootstrapError {
+    fn from(_: SqlCacheError) -> Self {
+        BootstrapError::CacheError
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct SqlCacheConfig {
+    /// Database connection string: either `sqlite://<path>` or (when
+    /// compiled with `postgres` feature) `postgres://<user>@<host>/<db>`
+    pub connection: String,
+    /// Format used for exporting cache data in `Sync` replies
+    pub data_format: DataFormat,
+}
+
+impl SqlCacheConfig {
+    pub const SQLITE_SCHEMES: [&'static str; 2] = ["sqlite://", "sqlite:"];
+    pub const POSTGRES_SCHEMES: [&'static str; 2] = ["postgres://", "postgresql://"];
+
+    /// Detects whether cache connection string points to SQL database rather
+    /// than to a file cache directory
+    pub fn is_sql_connection(connection: &str) -> bool {
+        Self::SQLITE_SCHEMES
+            .iter()
+            .chain(Self::POSTGRES_SCHEMES.iter())
+            .any(|scheme| connection.starts_with(scheme))
+    }
+
+    fn sqlite_path(&self) -> Option<PathBuf> {
+        Self::SQLITE_SCHEMES.iter().find_map(|scheme| {
+            if self.connection.starts_with(scheme) {
+                Some(PathBuf::from(&self.connection[scheme.len()..]))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+enum SqlConnection {
+    Sqlite(SqliteConnection),
+    #[cfg(feature = "postgres")]
+    Postgres(PgConnection),
+}
+
+/// Runs the same diesel query code against any of the supported backends
+macro_rules! with_connection {
+    ($connection:expr, |$conn:ident| $body:expr) => {
+        match $connection {
+            SqlConnection::Sqlite($conn) => $body,
+            #[cfg(feature = "postgres")]
+            SqlConnection::Postgres($conn) => $body,
+        }
+    };
+}
+
+/// Removes all rows related to a given asset; must be run inside a transaction
+macro_rules! delete_asset_rows {
+    ($conn:ident, $id:expr) => {{
+        diesel::delete(ledger::table.filter(ledger::asset_id.eq($id))).execute($conn)?;
+        diesel::delete(prune_rights::table.filter(prune_rights::asset_id.eq($id)))
+            .execute($conn)?;
+        diesel::delete(allocations::table.filter(allocations::asset_id.eq($id))).execute($conn)?;
+        diesel::delete(issues::table.filter(issues::asset_id.eq($id))).execute($conn)?;
+        diesel::delete(assets::table.filter(assets::id.eq($id))).execute($conn)?;
+    }};
+}
+
+/// Brings rows of a table in sync with the new asset state: rows which were
+/// removed or changed are deleted, changed and new rows are inserted, while
+/// unchanged rows are left intact; must be run inside a transaction
+macro_rules! sync_rows {
+    ($conn:ident, $table:ident, $old:expr, $new:expr, |$row:ident| $key:expr) => {{
+        let old = $old
+            .iter()
+            .map(|$row| ($key, $row))
+            .collect::<HashMap<_, _>>();
+        let new = $new
+            .iter()
+            .map(|$row| ($key, $row))
+            .collect::<HashMap<_, _>>();
+        for (key, row) in &old {
+            if new.get(key) != Some(row) {
+                diesel::delete($table::table.find(key.clone())).execute($conn)?;
+            }
+        }
+        for (key, row) in &new {
+            if old.get(key) != Some(row) {
+                diesel::insert_into($table::table)
+                    .values(*row)
+                    .execute($conn)?;
+            }
+        }
+    }};
+}
+
+/// Keeps fungible assets data in a relational database (SQLite or
+/// PostgreSQL). All reads are served from the in-memory copy loaded on start,
+/// while all modifications are written through to the database in a single
+/// transaction per asset.
+pub struct SqlCache {
+    config: SqlCacheConfig,
+    connection: SqlConnection,
+    assets: HashMap<ContractId, Asset>,
+}
+
+impl SqlCache {
+    pub fn new(config: SqlCacheConfig) -> Result<Self, SqlCacheError> {
+        debug!("Instantiating RGB fungible assets storage (SQL database) ...");
+
+        let connection = if let Some(path) = config.sqlite_path() {
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                if !dir.exists() {
+                    debug!(
+                        "RGB fungible assets database directory '{:?}' is not found; creating one",
+                        dir
+                    );
+                    fs::create_dir_all(dir)?;
+                }
+            }
+            let path = path.to_str().ok_or(SqlCacheError::DataIntegrity(
+                "Non-unicode SQLite database path".to_string(),
+            ))?;
+            SqlConnection::Sqlite(SqliteConnection::establish(path)?)
+        } else {
+            Self::postgres_connection(&config)?
+        };
+
+        debug!("Running database migrations ...");
+        with_connection!(&connection, |conn| embedded_migrations::run(conn))?;
+
+        let mut me = Self {
+            config,
+            connection,
+            assets: map![],
+        };
+        me.load()?;
+
+        Ok(me)
+    }
+
+    #[cfg(feature = "postgres")]
+    fn postgres_connection(config: &SqlCacheConfig) -> Result<SqlConnection, SqlCacheError> {
+        Ok(SqlConnection::Postgres(PgConnection::establish(
+            &config.connection,
+        )?))
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    fn postgres_connection(config: &SqlCacheConfig) -> Result<SqlConnection, SqlCacheError> {
+        Err(SqlCacheError::UnsupportedBackend(config.connection.clone()))
+    }
+
+    fn load(&mut self) -> Result<(), SqlCacheError> {
+        debug!("Reading assets information ...");
+        let (asset_rows, issue_rows, allocation_rows, prune_right_rows, ledger_rows) =
+            with_connection!(&self.connection, |conn| (
+                assets::table.load::<AssetRow>(conn)?,
+                issues::table
+                    .order((issues::asset_id, issues::series, issues::position))
+                    .load::<IssueRow>(conn)?,
+                allocations::table
+                    .order((
+                        allocations::asset_id,
+                        allocations::node_id,
+                        allocations::assignment_index,
+                    ))
+                    .load::<AllocationRow>(conn)?,
+                prune_rights::table.load::<PruneRightRow>(conn)?,
+                ledger::table
+                    .order((ledger::asset_id, ledger::position))
+                    .load::<LedgerRow>(conn)?,
+            ));
+
+        let mut issues = HashMap::<String, Vec<IssueRow>>::new();
+        for row in issue_rows {
+            issues
+                .entry(row.asset_id.clone())
+                .or_insert(vec![])
+                .push(row);
+        }
+        let mut allocations = HashMap::<String, Vec<AllocationRow>>::new();
+        for row in allocation_rows {
+            allocations
+                .entry(row.asset_id.clone())
+                .or_insert(vec![])
+                .push(row);
+        }
+
+        let mut prune_rights = HashMap::<String, Vec<PruneRightRow>>::new();
+        for row in prune_right_rows {
+            prune_rights
+                .entry(row.asset_id.clone())
+                .or_insert(vec![])
+                .push(row);
+        }
+
+        let mut ledgers = HashMap::<String, Vec<LedgerRow>>::new();
+        for row in ledger_rows {
+            ledgers
+                .entry(row.asset_id.clone())
+                .or_insert(vec![])
+                .push(row);
+        }
+
+        self.assets = asset_rows
+            .into_iter()
+            .map(|row| {
+                let issues = issues.remove(&row.id).unwrap_or_default();
+                let allocations = allocations.remove(&row.id).unwrap_or_default();
+                let prune_rights = prune_rights.remove(&row.id).unwrap_or_default();
+                let ledger = ledgers.remove(&row.id).unwrap_or_default();
+                let asset = asset_from_rows(row, issues, allocations, prune_rights, ledger)?;
+                Ok((*asset.id(), asset))
+            })
+            .collect::<Result<_, SqlCacheError>>()?;
+        Ok(())
+    }
+
+    fn save_asset(&self, old: Option<&Asset>, asset: &Asset) -> Result<(), SqlCacheError> {
+        trace!("Saving asset {} information ...", asset.id());
+        let (asset_row, issue_rows, allocation_rows, prune_right_rows, ledger_rows) =
+            rows_from_asset(asset)?;
+        let (old_row, old_issues, old_allocations, old_prune_rights, old_ledger) = match old {
+            Some(old) => {
+                let (row, issues, allocations, prune_rights, ledger) = rows_from_asset(old)?;
+                (Some(row), issues, allocations, prune_rights, ledger)
+            }
+            None => (None, vec![], vec![], vec![], vec![]),
+        };
+        with_connection!(&self.connection, |conn| conn
+            .transaction::<_, SqlCacheError, _>(|| {
+                match old_row {
+                    None => {
+                        diesel::insert_into(assets::table)
+                            .values(&asset_row)
+                            .execute(conn)?;
+                    }
+                    Some(ref old_row) if *old_row != asset_row => {
+                        diesel::update(assets::table.find(&asset_row.id))
+                            .set(&asset_row)
+                            .execute(conn)?;
+                    }
+                    Some(_) => {}
+                }
+                sync_rows!(conn, issues, old_issues, issue_rows, |row| (
+                    row.asset_id.clone(),
+                    row.series,
+                    row.position
+                ));
+                sync_rows!(conn, allocations, old_allocations, allocation_rows, |row| (
+                    row.asset_id.clone(),
+                    row.txid.clone(),
+                    row.vout,
+                    row.node_id.clone(),
+                    row.assignment_index
+                ));
+                sync_rows!(
+                    conn,
+                    prune_rights,
+                    old_prune_rights,
+                    prune_right_rows,
+                    |row| (row.asset_id.clone(), row.txid.clone(), row.vout)
+                );
+                sync_rows!(conn, ledger, old_ledger, ledger_rows, |row| (
+                    row.asset_id.clone(),
+                    row.position
+                ));
+                Ok(())
+            }))
+    }
+
+    fn delete_asset(&self, id: ContractId) -> Result<(), SqlCacheError> {
+        trace!("Removing asset {} information ...", id);
+        let id = id.to_hex();
+        with_connection!(&self.connection, |conn| conn
+            .transaction::<_, SqlCacheError, _>(|| {
+                delete_asset_rows!(conn, &id);
+                Ok(())
+            }))
+    }
+
+    pub fn export(&self) -> Result<Vec<u8>, SqlCacheError> {
+        trace!("Exporting assets information ...");
+        let assets = self.assets.values().collect::<Vec<&Asset>>();
+        Ok(match self.config.data_format {
+            DataFormat::Yaml => serde_yaml::to_vec(&assets)?,
+            DataFormat::Json => serde_json::to_vec(&assets)?,
+            DataFormat::Toml => toml::to_vec(&assets)?,
+            DataFormat::StrictEncode => {
+                strict_encode(&assets.into_iter().cloned().collect::<Vec<Asset>>())?
+            }
+        })
+    }
+}
+
+impl Cache for SqlCache {
+    type Error = CacheError;
+
+    fn assets(&self) -> Result<Vec<&Asset>, CacheError> {
+        Ok(self.assets.values().collect())
+    }
+
+    #[inline]
+    fn asset(&self, id: ContractId) -> Result<&Asset, CacheError> {
+        Ok(self.assets.get(&id).ok_or(CacheError::DataIntegrityError(
+            "Asset is not known".to_string(),
+        ))?)
+    }
+
+    #[inline]
+    fn has_asset(&self, id: ContractId) -> Result<bool, CacheError> {
+        Ok(self.assets.contains_key(&id))
+    }
+
+    fn add_asset(&mut self, asset: Asset) -> Result<bool, CacheError> {
+        self.save_asset(self.assets.get(asset.id()), &asset)?;
+        Ok(self.assets.insert(*asset.id(), asset).is_some())
+    }
+
+    fn remove_asset(&mut self, id: ContractId) -> Result<bool, CacheError> {
+        self.delete_asset(id)?;
+        Ok(self.assets.remove(&id).is_some())
+    }
+
+    #[inline]
+    fn export(&self) -> Result<Vec<u8>, CacheError> {
+        Ok(SqlCache::export(self)?)
+    }
+}
+
+fn asset_from_rows(
+    row: AssetRow,
+    issues: Vec<IssueRow>,
+    allocations: Vec<AllocationRow>,
+    prune_rights: Vec<PruneRightRow>,
+    ledger: Vec<LedgerRow>,
+) -> Result<Asset, SqlCacheError> {
+    let fractional_bits = row.fractional_bits as u8;
+    let coins = |sats: &str| -> Result<Coins, SqlCacheError> {
+        Ok(Coins::with_sats_precision(
+            amount_from_str(sats)?,
+            fractional_bits,
+        ))
+    };
+    let network = bp::Network::from_str(&row.network)
+        .map_err(|_| SqlCacheError::DataIntegrity(format!("Unknown network {}", row.network)))?;
+
+    let mut known_issues = Vec::<LinkedList<Issue>>::new();
+    for issue in issues {
+        let series = issue.series as usize;
+        if known_issues.len() <= series {
+            known_issues.resize(series + 1, LinkedList::new());
+        }
+        known_issues[series].push_back(Issue {
+            id: NodeId::from_hex(&issue.issue_id)?,
+            txo: issue.txo.as_deref().map(outpoint_from_str).transpose()?,
+            supply: coins(&issue.supply)?,
+        });
+    }
+
+    let mut known_allocations = BTreeMap::<OutPoint, Vec<Allocation>>::new();
+    for allocation in allocations {
+        let outpoint = OutPoint {
+            txid: Txid::from_hex(&allocation.txid)?,
+            vout: allocation.vout as u32,
+        };
+        let amount = amount::Revealed::strict_decode(
+            &Vec::<u8>::from_hex(&allocation.revealed_amount)?[..],
+        )?;
+        if amount.amount != amount_from_str(&allocation.amount)? {
+            Err(SqlCacheError::DataIntegrity(format!(
+                "Allocation amount does not match revealed amount for {}",
+                allocation.node_id
+            )))?
+        }
+        known_allocations
+            .entry(outpoint)
+            .or_insert(vec![])
+            .push(Allocation {
+                node_id: NodeId::from_hex(&allocation.node_id)?,
+                index: allocation.assignment_index as u16,
+                amount,
+            });
+    }
+
+    let mut known_prune_rights = BTreeMap::<OutPoint, PruneRight>::new();
+    for prune_right in prune_rights {
+        known_prune_rights.insert(
+            OutPoint {
+                txid: Txid::from_hex(&prune_right.txid)?,
+                vout: prune_right.vout as u32,
+            },
+            PruneRight {
+                node_id: NodeId::from_hex(&prune_right.node_id)?,
+                index: prune_right.assignment_index as u16,
+            },
+        );
+    }
+
+    let mut known_ledger = Vec::<LedgerEntry>::with_capacity(ledger.len());
+    for entry in ledger {
+        known_ledger.push(LedgerEntry {
+            operation: entry.operation.parse().map_err(|_| {
+                SqlCacheError::DataIntegrity(format!(
+                    "Unknown ledger operation {}",
+                    entry.operation
+                ))
+            })?,
+            node_id: NodeId::from_hex(&entry.node_id)?,
+            txid: Txid::from_hex(&entry.txid)?,
+            outpoint: entry
+                .outpoint
+                .as_deref()
+                .map(outpoint_from_str)
+                .transpose()?,
+            amount: amount_from_str(&entry.amount)?,
+            counterparty: entry
+                .counterparty
+                .as_deref()
+                .map(OutpointHash::from_hex)
+                .transpose()?,
+            timestamp: entry.timestamp,
+            pending: entry.pending,
+        });
+    }
+
+    Ok(Asset::with(
+        ContractId::from_hex(&row.id)?,
+        row.ticker,
+        row.name,
+        row.description,
+        Supply {
+            known_circulating: coins(&row.known_circulating)?,
+            total: row.total_supply.as_deref().map(coins).transpose()?,
+        },
+        coins(&row.dust_limit)?,
+        network,
+        fractional_bits,
+        row.date,
+        row.unspent_issue_txo
+            .as_deref()
+            .map(outpoint_from_str)
+            .transpose()?,
+        known_issues,
+        known_allocations,
+        known_prune_rights,
+        known_ledger,
+    ))
+}
+
+fn rows_from_asset(
+    asset: &Asset,
+) -> Result<
+    (
+        AssetRow,
+        Vec<IssueRow>,
+        Vec<AllocationRow>,
+        Vec<PruneRightRow>,
+        Vec<LedgerRow>,
+    ),
+    SqlCacheError,
+> {
+    let asset_id = asset.id().to_hex();
+    let asset_row = AssetRow {
+        id: asset_id.clone(),
+        ticker: asset.ticker().clone(),
+        name: asset.name().clone(),
+        description: asset.description().clone(),
+        network: asset.network().to_string(),
+        fractional_bits: *asset.fractional_bits() as i16,
+        date: *asset.date(),
+        dust_limit: asset.dust_limit().sats().to_string(),
+        known_circulating: asset.supply().known_circulating.sats().to_string(),
+        total_supply: asset
+            .supply()
+            .total
+            .as_ref()
+            .map(|coins| coins.sats().to_string()),
+        unspent_issue_txo: asset.unspent_issue_txo().as_ref().map(outpoint_to_string),
+    };
+
+    let issue_rows = asset
+        .known_issues()
+        .iter()
+        .enumerate()
+        .flat_map(|(series, list)| {
+            let asset_id = asset_id.clone();
+            list.iter()
+                .enumerate()
+                .map(move |(position, issue)| IssueRow {
+                    asset_id: asset_id.clone(),
+                    series: series as i32,
+                    position: position as i32,
+                    issue_id: issue.id.to_hex(),
+                    txo: issue.txo.as_ref().map(outpoint_to_string),
+                    supply: issue.supply.sats().to_string(),
+                })
+        })
+        .collect();
+
+    let mut allocation_rows = vec![];
+    for (outpoint, allocations) in asset.known_allocations() {
+        for allocation in allocations {
+            allocation_rows.push(AllocationRow {
+                asset_id: asset_id.clone(),
+                txid: outpoint.txid.to_hex(),
+                vout: outpoint.vout as i32,
+                node_id: allocation.node_id.to_hex(),
+                assignment_index: allocation.index as i32,
+                amount: allocation.amount.amount.to_string(),
+                revealed_amount: strict_encode(&allocation.amount)?.to_hex(),
+            });
+        }
+    }
+
+    let prune_right_rows = asset
+        .known_prune_rights()
+        .iter()
+        .map(|(outpoint, prune_right)| PruneRightRow {
+            asset_id: asset_id.clone(),
+            txid: outpoint.txid.to_hex(),
+            vout: outpoint.vout as i32,
+            node_id: prune_right.node_id.to_hex(),
+            assignment_index: prune_right.index as i32,
+        })
+        .collect();
+
+    let ledger_rows = asset
+        .known_ledger()
+        .iter()
+        .enumerate()
+        .map(|(position, entry)| LedgerRow {
+            asset_id: asset_id.clone(),
+            position: position as i32,
+            operation: entry.operation.to_string(),
+            node_id: entry.node_id.to_hex(),
+            txid: entry.txid.to_hex(),
+            outpoint: entry.outpoint.as_ref().map(outpoint_to_string),
+            amount: entry.amount.to_string(),
+            counterparty: entry.counterparty.as_ref().map(ToHex::to_hex),
+            timestamp: entry.timestamp,
+            pending: entry.pending,
+        })
+        .collect();
+
+    Ok((
+        asset_row,
+        issue_rows,
+        allocation_rows,
+        prune_right_rows,
+        ledger_rows,
+    ))
+}
+
+fn amount_from_str(s: &str) -> Result<Amount, SqlCacheError> {
+    s.parse()
+        .map_err(|_| SqlCacheError::DataIntegrity(format!("Broken amount value {}", s)))
+}
+
+#[inline]
+fn outpoint_to_string(outpoint: &OutPoint) -> String {
+    format!("{}:{}", outpoint.txid.to_hex(), outpoint.vout)
+}
+
+fn outpoint_from_str(s: &str) -> Result<OutPoint, SqlCacheError> {
+    let mut split = s.rsplitn(2, ':');
+    match (split.next(), split.next()) {
+        (Some(vout), Some(txid)) => Ok(OutPoint {
+            txid: Txid::from_hex(txid)?,
+            vout: vout
+                .parse()
+                .map_err(|_| SqlCacheError::DataIntegrity(format!("Broken outpoint {}", s)))?,
+        }),
+        _ => Err(SqlCacheError::DataIntegrity(format!(
+            "Broken outpoint {}",
+            s
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use lnpbp::bitcoin::hashes::Hash;
+    use lnpbp::bp::blind::OutpointReveal;
+    use lnpbp::client_side_validation::Conceal;
+    use lnpbp::rgb::seal;
+
+    use crate::fungible::LedgerOperation;
+
+    fn cache() -> SqlCache {
+        SqlCache::new(SqlCacheConfig {
+            connection: "sqlite::memory:".to_string(),
+            data_format: DataFormat::Yaml,
+        })
+        .expect("In-memory SQLite cache must be always available")
+    }
+
+    fn outpoint(no: u8) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_inner([no; 32]),
+            vout: no as u32,
+        }
+    }
+
+    fn revealed_amount(amount: Amount) -> amount::Revealed {
+        let seal = seal::Revealed::TxOutpoint(OutpointReveal::from(outpoint(0xFF)));
+        match AssignmentsVariant::zero_balanced(vec![], vec![(seal, amount)], vec![]) {
+            AssignmentsVariant::DiscreteFiniteField(set) => set
+                .into_iter()
+                .next()
+                .and_then(|assignment| assignment.assigned_state().cloned())
+                .expect("Zero-balanced assignment must have revealed amount"),
+            _ => unreachable!(),
+        }
+    }
+
+    fn ledger_entry(operation: LedgerOperation, no: u8, amount: Amount) -> LedgerEntry {
+        LedgerEntry {
+            operation,
+            node_id: NodeId::from_inner([no; 32]),
+            txid: Txid::from_inner([no; 32]),
+            outpoint: match operation {
+                LedgerOperation::Outgoing => None,
+                _ => Some(outpoint(no)),
+            },
+            amount,
+            counterparty: match operation {
+                LedgerOperation::Outgoing => Some(OutpointReveal::from(outpoint(no)).conceal()),
+                _ => None,
+            },
+            timestamp: NaiveDateTime::from_timestamp(1_593_561_600 + no as i64, 0),
+            pending: operation != LedgerOperation::Incoming,
+        }
+    }
+
+    fn asset() -> Asset {
+        let id = ContractId::from_inner([1; 32]);
+        let issue_id = NodeId::from_inner([1; 32]);
+        Asset::with(
+            id,
+            "TST".to_string(),
+            "Test asset".to_string(),
+            Some("Asset used in SQL cache tests".to_string()),
+            Supply {
+                known_circulating: Coins::with_sats_precision(u64::MAX - 1, 8),
+                total: Some(Coins::with_sats_precision(u64::MAX, 8)),
+            },
+            Coins::with_sats_precision(1000, 8),
+            bp::Network::from(bitcoin::Network::Testnet),
+            8,
+            NaiveDateTime::from_timestamp(1_593_561_600, 0),
+            Some(outpoint(2)),
+            vec![vec![Issue {
+                id: issue_id,
+                txo: Some(outpoint(2)),
+                supply: Coins::with_sats_precision(u64::MAX - 1, 8),
+            }]
+            .into_iter()
+            .collect()],
+            bmap! {
+                outpoint(3) => vec![
+                    Allocation {
+                        node_id: issue_id,
+                        index: 0,
+                        amount: revealed_amount(u64::MAX - 2),
+                    },
+                    Allocation {
+                        node_id: issue_id,
+                        index: 1,
+                        amount: revealed_amount(1),
+                    },
+                ]
+            },
+            bmap! {
+                outpoint(4) => PruneRight {
+                    node_id: issue_id,
+                    index: 0,
+                }
+            },
+            vec![ledger_entry(LedgerOperation::Incoming, 3, u64::MAX - 2)],
+        )
+    }
+
+    fn reloaded(cache: &mut SqlCache) -> HashMap<ContractId, Asset> {
+        cache.assets = map![];
+        cache.load().expect("Cache data must be readable");
+        cache.assets.clone()
+    }
+
+    #[test]
+    fn asset_round_trip() {
+        let mut cache = cache();
+        let asset = asset();
+
+        assert_eq!(cache.add_asset(asset.clone()).unwrap(), false);
+        let assets = reloaded(&mut cache);
+        assert_eq!(assets.len(), 1);
+        assert_eq!(assets.get(asset.id()), Some(&asset));
+    }
+
+    #[test]
+    fn asset_update() {
+        let mut cache = cache();
+        let mut asset = asset();
+        cache.add_asset(asset.clone()).unwrap();
+
+        // Changing, adding and removing rows in all of the asset tables
+        asset.add_allocation(
+            outpoint(5),
+            NodeId::from_inner([5; 32]),
+            0,
+            revealed_amount(5),
+        );
+        asset.remove_allocation(
+            outpoint(3),
+            NodeId::from_inner([1; 32]),
+            1,
+            revealed_amount(1),
+        );
+        asset.record_outgoing(
+            NodeId::from_inner([6; 32]),
+            Txid::from_inner([6; 32]),
+            &[outpoint(3)],
+            vec![(OutpointReveal::from(outpoint(7)).conceal(), 7)],
+        );
+        asset.confirm_spending(outpoint(3));
+
+        assert_eq!(cache.add_asset(asset.clone()).unwrap(), true);
+        assert_eq!(cache.asset(*asset.id()).unwrap(), &asset);
+        let assets = reloaded(&mut cache);
+        assert_eq!(assets.get(asset.id()), Some(&asset));
+
+        assert_eq!(cache.remove_asset(*asset.id()).unwrap(), true);
+        assert!(reloaded(&mut cache).is_empty());
+    }
+}
@@ -38,14 +38,26 @@ pub struct Opts {
     #[clap(short, long, default_value = RGB_DATA_DIR, env = "RGB_DATA_DIR")]
     pub data_dir: String,
 
-    /// Connection string to stash (exact format depends on used storage engine)
-    #[clap(short = "s", long = "stash", default_value = FUNGIBLED_CACHE, env = "RGB_FUNGIBLED_CACHE")]
+    /// Connection string to the asset cache: either a directory for the file
+    /// cache, or a `sqlite://<path>` or `postgres://<user>@<host>/<db>`
+    /// database URL (PostgreSQL requires `postgres` feature)
+    #[clap(short, long, default_value = FUNGIBLED_CACHE, env = "RGB_FUNGIBLED_CACHE")]
     pub cache: String,
 
-    /// Data format for cache storage (valid only if file storage is used)
+    /// Data format for file cache storage and cache data export
     #[clap(short, long, default_value = "yaml", env = "RGB_FUNGIBLED_FORMAT")]
     pub format: DataFormat,
 
+    /// Append cache updates to a journal instead of rewriting the whole cache
+    /// file on each change (valid only if file storage is used)
+    #[clap(short, long)]
+    pub journal: bool,
+
+    /// Directory for the invoice store, keeping blinding data for the
+    /// outpoints used in invoices and as transfer change outputs
+    #[clap(long, default_value = FUNGIBLED_INVOICES, env = "RGB_FUNGIBLED_INVOICES")]
+    pub invoices: String,
+
     /// ZMQ socket address string for REQ/REP API
     #[clap(
         long = "rpc",
@@ -93,6 +105,8 @@ pub struct Config {
     pub data_dir: PathBuf,
     pub cache: String,
     pub format: DataFormat,
+    pub journal: bool,
+    pub invoices: PathBuf,
     pub rpc_endpoint: SocketLocator,
     pub pub_endpoint: SocketLocator,
     pub stash_rpc: SocketLocator,
@@ -105,10 +119,13 @@ impl From<Opts> for Config {
         let mut me = Self {
             verbose: opts.verbose,
             network: opts.network,
+            format: opts.format,
+            journal: opts.journal,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
         me.cache = me.parse_param(opts.cache);
+        me.invoices = me.parse_param(opts.invoices);
         me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
         me.pub_endpoint = me.parse_param(opts.pub_endpoint);
         me.stash_rpc = me.parse_param(opts.stash_rpc);
@@ -126,6 +143,10 @@ impl Default for Config {
                 .expect("Error in RGB_DATA_DIR constant value"),
             cache: FUNGIBLED_CACHE.to_string(),
             format: DataFormat::Yaml,
+            journal: false,
+            invoices: FUNGIBLED_INVOICES
+                .parse()
+                .expect("Error in FUNGIBLED_INVOICES constant value"),
             rpc_endpoint: "ipc:/tmp"
                 .parse()
                 .expect("Error in STASHD_RPC_ENDPOINT constant value"),
@@ -0,0 +1,350 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::io;
+
+use lnpbp::rgb::Amount;
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+
+use crate::error::{ParseError, ServiceErrorDomain};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum AmountError {
+    /// Amount has more fractional digits than allowed by the asset precision
+    PrecisionExceeded { decimals: u8, precision: u8 },
+
+    /// Amount does not fit into 64-bit atomic value
+    Overflow,
+}
+
+impl From<AmountError> for ServiceErrorDomain {
+    fn from(err: AmountError) -> Self {
+        ServiceErrorDomain::Schema(format!("Wrong asset amount: {}", err))
+    }
+}
+
+/// Exact decimal amount of asset coins in its human-readable form (like
+/// `12.345`), which is independent from the asset precision. It is converted
+/// into atomic (integer) asset amount only when the asset precision is known,
+/// so unlike floating-point numbers it never loses or distorts any digits.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct AccountingAmount {
+    /// All significant digits of the amount as an integer value
+    digits: u64,
+    /// Number of `digits` which belong to the fractional part
+    decimals: u8,
+}
+
+impl AccountingAmount {
+    /// Maximum number of fractional digits which may be represented with
+    /// 64-bit atomic amounts
+    pub const MAX_DECIMALS: u8 = 19;
+
+    /// Constructs human-readable amount from the atomic amount using given
+    /// asset precision
+    #[inline]
+    pub fn from_atomic(atomic: Amount, precision: u8) -> Self {
+        Self {
+            digits: atomic,
+            decimals: precision,
+        }
+        .normalized()
+    }
+
+    /// Converts amount into atomic (integer) asset amount for an asset with
+    /// given precision. Fails if the amount has more fractional digits than
+    /// the precision allows or if the atomic value overflows.
+    pub fn atomic(&self, precision: u8) -> Result<Amount, AmountError> {
+        if self.decimals > precision {
+            Err(AmountError::PrecisionExceeded {
+                decimals: self.decimals,
+                precision,
+            })?
+        }
+        if self.digits == 0 {
+            return Ok(0);
+        }
+        10u64
+            .checked_pow((precision - self.decimals) as u32)
+            .and_then(|factor| self.digits.checked_mul(factor))
+            .ok_or(AmountError::Overflow)
+    }
+
+    /// Number of fractional digits in the amount (trailing zeros are never
+    /// counted)
+    #[inline]
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    #[inline]
+    pub fn is_zero(&self) -> bool {
+        self.digits == 0
+    }
+
+    fn normalized(mut self) -> Self {
+        while self.decimals > 0 && self.digits % 10 == 0 {
+            self.digits /= 10;
+            self.decimals -= 1;
+        }
+        if self.digits == 0 {
+            self.decimals = 0;
+        }
+        self
+    }
+}
+
+impl From<u64> for AccountingAmount {
+    #[inline]
+    fn from(coins: u64) -> Self {
+        Self {
+            digits: coins,
+            decimals: 0,
+        }
+    }
+}
+
+impl Display for AccountingAmount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.decimals == 0 {
+            return write!(f, "{}", self.digits);
+        }
+        let decimals = self.decimals as usize;
+        let digits = format!("{:0width$}", self.digits, width = decimals + 1);
+        let (int, fract) = digits.split_at(digits.len() - decimals);
+        write!(f, "{}.{}", int, fract)
+    }
+}
+
+impl FromStr for AccountingAmount {
+    type Err = ParseError;
+
+    /// Parses decimal amount like `1`, `0.25`, `.5` or `1_000'000.5`; `_`
+    /// and `'` may be used as digit group separators.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().replace(|c| c == '_' || c == '\'', "");
+        let mut parts = s.splitn(2, '.');
+        let int = parts.next().unwrap_or_default();
+        let fract = parts.next();
+        if (int.is_empty() && fract.map(str::is_empty).unwrap_or(true))
+            || !int
+                .chars()
+                .chain(fract.unwrap_or_default().chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            Err(ParseError)?
+        }
+        let fract = fract.unwrap_or_default().trim_end_matches('0');
+        if fract.len() > Self::MAX_DECIMALS as usize {
+            Err(ParseError)?
+        }
+
+        let int = if int.is_empty() {
+            0
+        } else {
+            int.parse::<u64>()?
+        };
+        let fract_value = if fract.is_empty() {
+            0
+        } else {
+            fract.parse::<u64>()?
+        };
+        let digits = 10u64
+            .checked_pow(fract.len() as u32)
+            .and_then(|factor| int.checked_mul(factor))
+            .and_then(|int| int.checked_add(fract_value))
+            .ok_or(ParseError)?;
+        Ok(Self {
+            digits,
+            decimals: fract.len() as u8,
+        }
+        .normalized())
+    }
+}
+
+impl Serialize for AccountingAmount {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountingAmount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct AmountVisitor;
+
+        impl<'de> Visitor<'de> for AmountVisitor {
+            type Value = AccountingAmount;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                formatter.write_str("a non-negative decimal amount")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(AccountingAmount::from(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                if v < 0 {
+                    Err(E::invalid_value(de::Unexpected::Signed(v), &self))
+                } else {
+                    Ok(AccountingAmount::from(v as u64))
+                }
+            }
+
+            /// Floating-point values are accepted for the compatibility with
+            /// JSON clients; we use the shortest decimal representation of
+            /// the value, i.e. exactly the number the client has written
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                v.to_string()
+                    .parse()
+                    .map_err(|_| E::invalid_value(de::Unexpected::Float(v), &self))
+            }
+        }
+
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+impl StrictEncode for AccountingAmount {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.digits, self.decimals))
+    }
+}
+
+impl StrictDecode for AccountingAmount {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        let digits = u64::strict_decode(&mut d)?;
+        let decimals = u8::strict_decode(&mut d)?;
+        if decimals > Self::MAX_DECIMALS {
+            Err(strict_encoding::Error::DataIntegrityError(format!(
+                "Accounting amount can't have more than {} decimals",
+                Self::MAX_DECIMALS
+            )))?
+        }
+        Ok(Self { digits, decimals }.normalized())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_round_trip(atomic: Amount, precision: u8, s: &str) {
+        let amount = AccountingAmount::from_atomic(atomic, precision);
+        assert_eq!(amount.to_string(), s);
+        let parsed = AccountingAmount::from_str(s).unwrap();
+        assert_eq!(parsed, amount);
+        assert_eq!(parsed.atomic(precision), Ok(atomic));
+    }
+
+    #[test]
+    fn round_trip_precision_0() {
+        assert_round_trip(0, 0, "0");
+        assert_round_trip(1, 0, "1");
+        assert_round_trip(1_000, 0, "1000");
+        assert_round_trip(u64::MAX, 0, "18446744073709551615");
+    }
+
+    #[test]
+    fn round_trip_precision_8() {
+        assert_round_trip(0, 8, "0");
+        assert_round_trip(1, 8, "0.00000001");
+        assert_round_trip(100_000_000, 8, "1");
+        assert_round_trip(150_000_000, 8, "1.5");
+        assert_round_trip(123_456_789, 8, "1.23456789");
+        assert_round_trip(u64::MAX, 8, "184467440737.09551615");
+    }
+
+    #[test]
+    fn round_trip_precision_18() {
+        assert_round_trip(0, 18, "0");
+        assert_round_trip(1, 18, "0.000000000000000001");
+        assert_round_trip(1_000_000_000_000_000_000, 18, "1");
+        assert_round_trip(1_500_000_000_000_000_000, 18, "1.5");
+        assert_round_trip(u64::MAX, 18, "18.446744073709551615");
+    }
+
+    #[test]
+    fn max_value_overflow() {
+        assert!(AccountingAmount::from_str("18446744073709551616").is_err());
+        assert!(AccountingAmount::from_str("184467440737.09551616").is_err());
+        assert!(AccountingAmount::from_str("18.446744073709551616").is_err());
+
+        let max = AccountingAmount::from_str("18446744073709551615").unwrap();
+        assert_eq!(max.atomic(0), Ok(u64::MAX));
+        assert_eq!(max.atomic(1), Err(AmountError::Overflow));
+        let max = AccountingAmount::from_str("18.446744073709551615").unwrap();
+        assert_eq!(max.atomic(18), Ok(u64::MAX));
+        assert_eq!(max.atomic(19), Err(AmountError::Overflow));
+        assert_eq!(
+            AccountingAmount::from(1).atomic(20),
+            Err(AmountError::Overflow)
+        );
+    }
+
+    #[test]
+    fn excess_fractional_digits() {
+        let amount = AccountingAmount::from_str("0.001").unwrap();
+        assert_eq!(amount.decimals(), 3);
+        assert_eq!(
+            amount.atomic(2),
+            Err(AmountError::PrecisionExceeded {
+                decimals: 3,
+                precision: 2
+            })
+        );
+        assert_eq!(
+            AccountingAmount::from_str("1.000000001").unwrap().atomic(8),
+            Err(AmountError::PrecisionExceeded {
+                decimals: 9,
+                precision: 8
+            })
+        );
+        assert_eq!(
+            AccountingAmount::from_str("1.0000000000000000001")
+                .unwrap()
+                .atomic(18),
+            Err(AmountError::PrecisionExceeded {
+                decimals: 19,
+                precision: 18
+            })
+        );
+        assert!(AccountingAmount::from_str("0.00000000000000000001").is_err());
+
+        // Trailing zeros are not significant
+        assert_eq!(
+            AccountingAmount::from_str("1.10000").unwrap().atomic(1),
+            Ok(11)
+        );
+    }
+}
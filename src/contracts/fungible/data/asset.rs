@@ -12,46 +12,53 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 use core::convert::TryFrom;
-use std::collections::{BTreeMap, LinkedList};
+use std::collections::{BTreeMap, BTreeSet, LinkedList};
+use std::io;
 
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use lnpbp::bitcoin;
 use lnpbp::bitcoin::hashes::Hash;
 use lnpbp::bp;
+use lnpbp::bp::blind::OutpointHash;
 use lnpbp::rgb::prelude::*;
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
 
-use super::schema::{AssignmentsType, FieldType};
-use super::{schema, SchemaError};
+use super::schema::{AssignmentsType, FieldType, TransitionType};
+use super::{schema, AccountingAmount, AmountError, LedgerEntry, LedgerOperation, SchemaError};
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Display, Default)]
 #[display_from(Debug)]
 pub struct Coins(Amount, u8);
 
 impl Coins {
+    /// Converts human-readable amount into atomic amount for the given
+    /// precision (number of decimal digits in the fractional part)
     #[inline]
-    pub fn transmutate(coins: f32, precision: u8) -> Amount {
-        Coins::with_value_precision(coins, precision).sats()
+    pub fn transmutate(coins: AccountingAmount, precision: u8) -> Result<Amount, AmountError> {
+        coins.atomic(precision)
     }
 
     #[inline]
-    pub fn with_asset_coins(asset: &Asset, coins: f32) -> Self {
-        let bits = asset.fractional_bits;
-        let full = (coins.trunc() as u64) << bits as u64;
-        let fract = coins.fract() as u64;
-        Self(full + fract, asset.fractional_bits)
+    pub fn with_asset_coins(asset: &Asset, coins: AccountingAmount) -> Result<Self, AmountError> {
+        Ok(Self(
+            coins.atomic(asset.fractional_bits)?,
+            asset.fractional_bits,
+        ))
     }
 
     #[inline]
-    fn with_sats_precision(sats: Amount, fractional_bits: u8) -> Self {
+    pub(crate) fn with_sats_precision(sats: Amount, fractional_bits: u8) -> Self {
         Self(sats, fractional_bits)
     }
 
     #[inline]
-    pub(crate) fn with_value_precision(value: f32, fractional_bits: u8) -> Self {
-        let fract = (value.fract() * 10u64.pow(fractional_bits as u32) as f32) as u64;
-        Self(value.trunc() as u64 + fract, fractional_bits)
+    pub(crate) fn with_value_precision(
+        value: AccountingAmount,
+        fractional_bits: u8,
+    ) -> Result<Self, AmountError> {
+        Ok(Self(value.atomic(fractional_bits)?, fractional_bits))
     }
 
     #[inline]
@@ -60,10 +67,8 @@ impl Coins {
     }
 
     #[inline]
-    pub fn coins(&self) -> f32 {
-        let full = self.0 >> self.1;
-        let fract = self.0 ^ (full << self.1);
-        full as f32 + fract as f32 / 10u64.pow(self.1 as u32) as f32
+    pub fn coins(&self) -> AccountingAmount {
+        AccountingAmount::from_atomic(self.0, self.1)
     }
 
     #[inline]
@@ -92,9 +97,13 @@ pub struct Asset {
     unspent_issue_txo: Option<bitcoin::OutPoint>,
     known_issues: Vec<LinkedList<Issue>>,
     known_allocations: BTreeMap<bitcoin::OutPoint, Vec<Allocation>>,
+    #[serde(default)]
+    known_prune_rights: BTreeMap<bitcoin::OutPoint, PruneRight>,
+    #[serde(default)]
+    known_ledger: Vec<LedgerEntry>,
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Display)]
+#[derive(Clone, Serialize, Deserialize, StrictEncode, StrictDecode, PartialEq, Debug, Display)]
 #[display_from(Debug)]
 pub struct Allocation {
     pub node_id: NodeId,
@@ -103,30 +112,342 @@ pub struct Allocation {
     pub amount: amount::Revealed,
 }
 
-#[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Debug, Display, Default)]
+/// Right to prune (burn) asset allocations, assigned to a seal controlled by
+/// the local party
+#[derive(
+    Clone, Serialize, Deserialize, StrictEncode, StrictDecode, PartialEq, Eq, Hash, Debug, Display,
+)]
+#[display_from(Debug)]
+pub struct PruneRight {
+    pub node_id: NodeId,
+    // Index of the assignment within the node
+    pub index: u16,
+}
+
+#[derive(
+    Clone,
+    Serialize,
+    Deserialize,
+    StrictEncode,
+    StrictDecode,
+    PartialEq,
+    Eq,
+    Hash,
+    Debug,
+    Display,
+    Default,
+)]
 #[display_from(Debug)]
 pub struct Supply {
     pub known_circulating: Coins,
     pub total: Option<Coins>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Display)]
+#[derive(
+    Clone, Debug, Serialize, Deserialize, StrictEncode, StrictDecode, PartialEq, Eq, Hash, Display,
+)]
 #[display_from(Debug)]
 pub struct Issue {
-    pub id: ContractId,
+    /// Genesis or secondary issue transition which has issued the supply
+    pub id: NodeId,
     /// A point that has to be monitored to detect next issuance
     pub txo: Option<bitcoin::OutPoint>,
     pub supply: Coins,
 }
 
+impl StrictEncode for Coins {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(strict_encode_list!(e; self.0, self.1))
+    }
+}
+
+impl StrictDecode for Coins {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self(
+            Amount::strict_decode(&mut d)?,
+            u8::strict_decode(&mut d)?,
+        ))
+    }
+}
+
+impl StrictEncode for Asset {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        // Linked lists and dates have no strict encoding, so we use vectors
+        // and unix timestamps instead
+        let known_issues = self
+            .known_issues
+            .iter()
+            .map(|issues| issues.iter().cloned().collect())
+            .collect::<Vec<Vec<Issue>>>();
+        Ok(strict_encode_list!(e;
+            self.id,
+            self.ticker,
+            self.name,
+            self.description,
+            self.supply,
+            self.dust_limit,
+            self.network(),
+            self.fractional_bits,
+            self.date.timestamp(),
+            self.unspent_issue_txo,
+            known_issues,
+            self.known_allocations,
+            self.known_prune_rights,
+            self.known_ledger
+        ))
+    }
+}
+
+impl StrictDecode for Asset {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: ContractId::strict_decode(&mut d)?,
+            ticker: String::strict_decode(&mut d)?,
+            name: String::strict_decode(&mut d)?,
+            description: Option::<String>::strict_decode(&mut d)?,
+            supply: Supply::strict_decode(&mut d)?,
+            dust_limit: Coins::strict_decode(&mut d)?,
+            network_magic: bp::Network::strict_decode(&mut d)?.as_magic(),
+            fractional_bits: u8::strict_decode(&mut d)?,
+            date: {
+                let timestamp = i64::strict_decode(&mut d)?;
+                NaiveDateTime::from_timestamp_opt(timestamp, 0).ok_or(
+                    strict_encoding::Error::DataIntegrityError(format!(
+                        "Asset issue date {} is out of range",
+                        timestamp
+                    )),
+                )?
+            },
+            unspent_issue_txo: Option::<bitcoin::OutPoint>::strict_decode(&mut d)?,
+            known_issues: Vec::<Vec<Issue>>::strict_decode(&mut d)?
+                .into_iter()
+                .map(|issues| issues.into_iter().collect())
+                .collect(),
+            known_allocations: BTreeMap::strict_decode(&mut d)?,
+            known_prune_rights: BTreeMap::strict_decode(&mut d)?,
+            known_ledger: Vec::<LedgerEntry>::strict_decode(&mut d)?,
+        })
+    }
+}
+
 impl Asset {
+    /// Constructs asset from its previously stored parts; used by cache
+    /// backends which keep asset data in a decomposed form
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn with(
+        id: ContractId,
+        ticker: String,
+        name: String,
+        description: Option<String>,
+        supply: Supply,
+        dust_limit: Coins,
+        network: bp::Network,
+        fractional_bits: u8,
+        date: NaiveDateTime,
+        unspent_issue_txo: Option<bitcoin::OutPoint>,
+        known_issues: Vec<LinkedList<Issue>>,
+        known_allocations: BTreeMap<bitcoin::OutPoint, Vec<Allocation>>,
+        known_prune_rights: BTreeMap<bitcoin::OutPoint, PruneRight>,
+        known_ledger: Vec<LedgerEntry>,
+    ) -> Self {
+        Self {
+            id,
+            ticker,
+            name,
+            description,
+            supply,
+            dust_limit,
+            network_magic: network.as_magic(),
+            fractional_bits,
+            date,
+            unspent_issue_txo,
+            known_issues,
+            known_allocations,
+            known_prune_rights,
+            known_ledger,
+        }
+    }
+
     #[inline]
     pub fn network(&self) -> bp::Network {
         bp::Network::from_magic(self.network_magic)
     }
 
-    pub fn add_issue(&self, _issue: Transition) -> Supply {
-        unimplemented!()
+    /// Registers secondary issuance (inflation) transition: appends it to
+    /// the known issues, updates the circulating supply and the issue control
+    /// output and adds all allocations with revealed seals. Seals defined
+    /// relatively to the witness transaction are resolved with `witness_txid`.
+    /// Returns updated asset supply.
+    pub fn add_issue(
+        &mut self,
+        issue: &Transition,
+        witness_txid: bitcoin::Txid,
+    ) -> Result<Supply, SchemaError> {
+        if issue.type_id() != Some(-TransitionType::Issue) {
+            Err(SchemaError::WrongTransitionType)?;
+        }
+        let supply = Coins::with_sats_precision(
+            issue.metadata().u64(-FieldType::IssuedSupply)?,
+            self.fractional_bits,
+        );
+        let known_circulating = self
+            .supply
+            .known_circulating
+            .sats()
+            .checked_add(supply.sats())
+            .ok_or(SchemaError::TotalSupplyExceeded)?;
+        if let Some(ref total) = self.supply.total {
+            if known_circulating > total.sats() {
+                Err(SchemaError::TotalSupplyExceeded)?;
+            }
+        }
+
+        let txo = issue
+            .known_seal_definitions_by_type(-AssignmentsType::Issue)
+            .first()
+            .map(|seal| seal_outpoint(seal, witness_txid));
+        let node_id = issue.node_id();
+        let new_issue = Issue {
+            id: node_id,
+            txo,
+            supply,
+        };
+        match self.known_issues.last_mut() {
+            Some(issues) => issues.push_back(new_issue),
+            None => self.known_issues.push(list! { new_issue }),
+        }
+        self.unspent_issue_txo = txo;
+        self.supply.known_circulating =
+            Coins::with_sats_precision(known_circulating, self.fractional_bits);
+
+        for variant in issue.assignments_by_type(-AssignmentsType::Assets) {
+            if let AssignmentsVariant::DiscreteFiniteField(tree) = variant {
+                for (index, assign) in tree.iter().enumerate() {
+                    if let Assignment::Revealed {
+                        seal_definition,
+                        assigned_state,
+                    } = assign
+                    {
+                        self.add_allocation(
+                            seal_outpoint(seal_definition, witness_txid),
+                            node_id,
+                            index as u16,
+                            assigned_state.clone(),
+                        );
+                    }
+                }
+            }
+        }
+        self.add_prune_rights(issue, witness_txid);
+
+        Ok(self.supply.clone())
+    }
+
+    /// Registers prune (burn) transition: removes the closed prune right and
+    /// pruned allocations, adds the remaining allocations and prune rights
+    /// with revealed seals and decreases the circulating supply by the
+    /// burned amount. Seals defined relatively to the witness transaction are
+    /// resolved with `witness_txid`. Returns updated asset supply.
+    pub fn add_prune(
+        &mut self,
+        prune: &Transition,
+        witness_txid: bitcoin::Txid,
+    ) -> Result<Supply, SchemaError> {
+        if prune.type_id() != Some(-TransitionType::Prune) {
+            Err(SchemaError::WrongTransitionType)?;
+        }
+
+        let mut pruned = 0u64;
+        let mut reallocated = 0u64;
+        for (node_id, closed) in prune.ancestors() {
+            if let Some(indexes) = closed.get(&-AssignmentsType::Prune) {
+                self.known_prune_rights.retain(|_, right| {
+                    right.node_id != *node_id || !indexes.contains(&right.index)
+                });
+            }
+            if let Some(indexes) = closed.get(&-AssignmentsType::Assets) {
+                for allocations in self.known_allocations.values_mut() {
+                    allocations.retain(|allocation| {
+                        if allocation.node_id == *node_id && indexes.contains(&allocation.index) {
+                            pruned += allocation.amount.amount;
+                            false
+                        } else {
+                            true
+                        }
+                    });
+                }
+            }
+        }
+        self.known_allocations
+            .retain(|_, allocations| !allocations.is_empty());
+
+        let node_id = prune.node_id();
+        for variant in prune.assignments_by_type(-AssignmentsType::Assets) {
+            if let AssignmentsVariant::DiscreteFiniteField(tree) = variant {
+                for (index, assign) in tree.iter().enumerate() {
+                    if let Assignment::Revealed {
+                        seal_definition,
+                        assigned_state,
+                    } = assign
+                    {
+                        if *seal_definition == schema::burn_seal() {
+                            continue;
+                        }
+                        reallocated += assigned_state.amount;
+                        self.add_allocation(
+                            seal_outpoint(seal_definition, witness_txid),
+                            node_id,
+                            index as u16,
+                            assigned_state.clone(),
+                        );
+                    }
+                }
+            }
+        }
+        self.add_prune_rights(prune, witness_txid);
+
+        let burned = pruned.saturating_sub(reallocated);
+        self.supply.known_circulating = Coins::with_sats_precision(
+            self.supply.known_circulating.sats().saturating_sub(burned),
+            self.fractional_bits,
+        );
+
+        Ok(self.supply.clone())
+    }
+
+    #[inline]
+    pub fn prune_right(&self, seal: &bitcoin::OutPoint) -> Option<&PruneRight> {
+        self.known_prune_rights.get(seal)
+    }
+
+    fn add_prune_rights(&mut self, node: &impl Node, witness_txid: bitcoin::Txid) {
+        let node_id = node.node_id();
+        for variant in node.assignments_by_type(-AssignmentsType::Prune) {
+            if let AssignmentsVariant::Declarative(set) = variant {
+                for (index, assign) in set.iter().enumerate() {
+                    if let Assignment::Revealed {
+                        seal_definition, ..
+                    } = assign
+                    {
+                        self.known_prune_rights.insert(
+                            seal_outpoint(seal_definition, witness_txid),
+                            PruneRight {
+                                node_id,
+                                index: index as u16,
+                            },
+                        );
+                    }
+                }
+            }
+        }
     }
 
     #[inline]
@@ -170,6 +491,154 @@ impl Asset {
         let allocations = self.known_allocations.entry(seal).or_insert(vec![]);
         allocations.remove_item(&old_allocation).is_some()
     }
+
+    /// Records assets received to a locally-controlled outpoint by the state
+    /// transition into the ledger; returns `false` if the allocation was
+    /// already recorded
+    pub fn record_incoming(
+        &mut self,
+        node_id: NodeId,
+        witness_txid: bitcoin::Txid,
+        outpoint: bitcoin::OutPoint,
+        amount: Amount,
+    ) -> bool {
+        if self.known_ledger.iter().any(|entry| {
+            entry.operation == LedgerOperation::Incoming
+                && entry.node_id == node_id
+                && entry.outpoint == Some(outpoint)
+                && entry.amount == amount
+        }) {
+            return false;
+        }
+        self.known_ledger.push(LedgerEntry {
+            operation: LedgerOperation::Incoming,
+            node_id,
+            txid: witness_txid,
+            outpoint: Some(outpoint),
+            amount,
+            counterparty: None,
+            timestamp: Utc::now().naive_utc(),
+            pending: false,
+        });
+        true
+    }
+
+    /// Records our transfer into the ledger as pending: spendings of all
+    /// known allocations on the input outpoints and amounts sent to each of
+    /// the other party seals. Inputs of the pending transfer are not
+    /// available for spending until the transfer is cancelled.
+    pub fn record_outgoing(
+        &mut self,
+        node_id: NodeId,
+        witness_txid: bitcoin::Txid,
+        inputs: &[bitcoin::OutPoint],
+        sent: Vec<(OutpointHash, Amount)>,
+    ) {
+        let timestamp = Utc::now().naive_utc();
+        for outpoint in inputs {
+            let amount = self
+                .known_allocations
+                .get(outpoint)
+                .map(|allocations| {
+                    allocations
+                        .iter()
+                        .fold(0u64, |sum, alloc| sum.saturating_add(alloc.amount.amount))
+                })
+                .unwrap_or_default();
+            self.known_ledger.push(LedgerEntry {
+                operation: LedgerOperation::Spending,
+                node_id,
+                txid: witness_txid,
+                outpoint: Some(*outpoint),
+                amount,
+                counterparty: None,
+                timestamp,
+                pending: true,
+            });
+        }
+        for (counterparty, amount) in sent {
+            self.known_ledger.push(LedgerEntry {
+                operation: LedgerOperation::Outgoing,
+                node_id,
+                txid: witness_txid,
+                outpoint: None,
+                amount,
+                counterparty: Some(counterparty),
+                timestamp,
+                pending: true,
+            });
+        }
+    }
+
+    /// Cancels our pending transfer (which witness transaction was not
+    /// published) by removing its ledger entries, so the inputs become
+    /// available for spending again; returns `false` if there is no pending
+    /// transfer with the given state transition
+    pub fn cancel_transfer(&mut self, node_id: NodeId) -> bool {
+        let len = self.known_ledger.len();
+        self.known_ledger
+            .retain(|entry| !(entry.pending && entry.node_id == node_id));
+        self.known_ledger.len() != len
+    }
+
+    /// Finalizes our pending transfers spending the outpoint, once it is
+    /// known to be spent by the mined witness transaction
+    pub fn confirm_spending(&mut self, outpoint: bitcoin::OutPoint) {
+        let transfers = self
+            .known_ledger
+            .iter()
+            .filter(|entry| {
+                entry.pending
+                    && entry.operation == LedgerOperation::Spending
+                    && entry.outpoint == Some(outpoint)
+            })
+            .map(|entry| entry.node_id)
+            .collect::<BTreeSet<_>>();
+        for entry in &mut self.known_ledger {
+            if transfers.contains(&entry.node_id) {
+                entry.pending = false;
+            }
+        }
+    }
+
+    /// Known allocations which are not yet spent by our transfers, with the
+    /// total asset amount on each of the outpoints
+    pub fn unspent_allocations(&self) -> BTreeMap<bitcoin::OutPoint, Amount> {
+        let spent = self.spent_outpoints();
+        self.known_allocations
+            .iter()
+            .filter(|(outpoint, _)| !spent.contains(outpoint))
+            .map(|(outpoint, allocations)| {
+                (
+                    *outpoint,
+                    allocations
+                        .iter()
+                        .fold(0u64, |sum, alloc| sum.saturating_add(alloc.amount.amount)),
+                )
+            })
+            .filter(|(_, amount)| *amount > 0)
+            .collect()
+    }
+
+    /// Total amount of the known allocations already spent by our transfers,
+    /// which are kept until they are forgotten
+    pub fn spent_amount(&self) -> Amount {
+        let spent = self.spent_outpoints();
+        self.known_allocations
+            .iter()
+            .filter(|(outpoint, _)| spent.contains(outpoint))
+            .flat_map(|(_, allocations)| allocations)
+            .fold(0u64, |sum, alloc| sum.saturating_add(alloc.amount.amount))
+    }
+
+    /// Outpoints spent by our transfers, including not yet confirmed ones
+    pub(crate) fn spent_outpoints(&self) -> BTreeSet<bitcoin::OutPoint> {
+        self.known_ledger
+            .iter()
+            .filter(|entry| entry.operation == LedgerOperation::Spending)
+            .filter_map(|entry| entry.outpoint)
+            .collect()
+    }
 }
 
 impl TryFrom<Genesis> for Asset {
@@ -188,7 +657,7 @@ impl TryFrom<Genesis> for Asset {
 
         let node_id = NodeId::from_inner(genesis.contract_id().into_inner());
         let issue = Issue {
-            id: genesis.contract_id(),
+            id: node_id,
             txo: genesis
                 .known_seal_definitions_by_type(-AssignmentsType::Issue)
                 .first()
@@ -216,6 +685,26 @@ impl TryFrom<Genesis> for Asset {
                 });
             }
         }
+        let mut known_prune_rights = BTreeMap::<bitcoin::OutPoint, PruneRight>::default();
+        for variant in genesis.assignments_by_type(-AssignmentsType::Prune) {
+            if let AssignmentsVariant::Declarative(set) = variant {
+                set.iter().enumerate().for_each(|(index, assign)| {
+                    if let Assignment::Revealed {
+                        seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal),
+                        ..
+                    } = assign
+                    {
+                        known_prune_rights.insert(
+                            outpoint_reveal.clone().into(),
+                            PruneRight {
+                                node_id,
+                                index: index as u16,
+                            },
+                        );
+                    }
+                });
+            }
+        }
         Ok(Self {
             id: genesis.contract_id(),
             network_magic: genesis.network().as_magic(),
@@ -235,11 +724,25 @@ impl TryFrom<Genesis> for Asset {
             ),
             fractional_bits,
             date: NaiveDateTime::from_timestamp(genesis_meta.i64(-FieldType::Timestamp)?, 0),
-            unspent_issue_txo: None,
+            unspent_issue_txo: issue.txo,
             known_issues: vec![list! { issue }],
             // we assume that each genesis allocation with revealed amount
             // and known seal (they are always revealed together) belongs to us
             known_allocations,
+            known_prune_rights,
+            known_ledger: vec![],
         })
     }
 }
+
+/// Resolves transaction output for a revealed seal; seals pointing to the
+/// witness transaction outputs are resolved with the given witness txid
+fn seal_outpoint(seal: &seal::Revealed, witness_txid: bitcoin::Txid) -> bitcoin::OutPoint {
+    match seal {
+        seal::Revealed::TxOutpoint(outpoint_reveal) => outpoint_reveal.clone().into(),
+        seal::Revealed::WitnessVout { vout, .. } => bitcoin::OutPoint {
+            txid: witness_txid,
+            vout: *vout as u32,
+        },
+    }
+}
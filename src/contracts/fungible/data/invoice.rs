@@ -11,14 +11,25 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+use bech32::{FromBase32, ToBase32};
+use chrono::{NaiveDateTime, Utc};
 use core::fmt::{Display, Formatter};
 use core::str::FromStr;
+use std::io;
 use url::Url;
 
 use lnpbp::bitcoin;
+use lnpbp::bitcoin::hashes::hex::{FromHex, ToHex};
+use lnpbp::bitcoin::hashes::{sha256, Hash};
+use lnpbp::bitcoin::secp256k1::{self, PublicKey, SecretKey, Signature};
 use lnpbp::bitcoin::Address;
+use lnpbp::bp;
 use lnpbp::bp::blind::OutpointHash;
 use lnpbp::rgb::{Bech32, ContractId, ToBech32};
+use lnpbp::strict_encoding::{self, strict_decode, strict_encode, StrictDecode, StrictEncode};
+
+use super::AccountingAmount;
+use crate::constants::RGB20_BECH32_HRP_INVOICE_COMPACT;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, Error, From)]
 #[display_from(Debug)]
@@ -39,6 +50,37 @@ pub enum Error {
     WrongAmountEncoding,
 
     WrongOutpoint,
+
+    WrongExpiryEncoding,
+
+    WrongNetworkEncoding,
+
+    WrongIdEncoding,
+
+    WrongPubkeyEncoding,
+
+    WrongSignatureEncoding,
+
+    /// Signature is provided without the public key or vice versa
+    IncompleteSignature,
+
+    WrongBech32Encoding,
+
+    WrongCompactEncoding,
+
+    /// Invoice has expired
+    Expired,
+
+    /// Invoice is issued for a different network than the one used by the
+    /// payer
+    NetworkMismatch,
+
+    /// Invoice signature does not match the invoice data and receiver key
+    InvalidSignature,
+
+    /// Invoice is not signed, while the payer expects it to be signed by a
+    /// known receiver key
+    NoSignature,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -53,11 +95,256 @@ pub enum Outpoint {
     Address(bitcoin::Address),
 }
 
+/// Receiver's signature over the invoice data, proving the invoice origin
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct InvoiceSignature {
+    pub pubkey: PublicKey,
+    pub signature: Signature,
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub struct Invoice {
     pub contract_id: ContractId,
     pub outpoint: Outpoint,
-    pub amount: f32,
+    pub amount: AccountingAmount,
+    /// Time after which the invoice must not be paid
+    pub expiry: Option<NaiveDateTime>,
+    pub network: Option<bp::Network>,
+    /// Merchant-provided description of the payment purpose
+    pub description: Option<String>,
+    /// Unique invoice identifier allowing the receiver to match payments
+    pub id: Option<u64>,
+    pub signature: Option<InvoiceSignature>,
+}
+
+impl Invoice {
+    /// Constructs invoice with all optional fields left empty
+    pub fn new(contract_id: ContractId, outpoint: Outpoint, amount: AccountingAmount) -> Self {
+        Self {
+            contract_id,
+            outpoint,
+            amount,
+            expiry: None,
+            network: None,
+            description: None,
+            id: None,
+            signature: None,
+        }
+    }
+
+    /// Hash of the invoice data covered by the signature: everything except
+    /// the signature itself, in the canonical URL form
+    pub fn signature_hash(&self) -> sha256::Hash {
+        sha256::Hash::hash(self.url(false).as_str().as_bytes())
+    }
+
+    /// Signs the invoice with the receiver's key, replacing any existing
+    /// signature
+    pub fn sign(&mut self, seckey: &SecretKey) {
+        let secp = secp256k1::Secp256k1::signing_only();
+        let message = secp256k1::Message::from_slice(&self.signature_hash()[..])
+            .expect("SHA256 hash is always a valid signature message");
+        self.signature = Some(InvoiceSignature {
+            pubkey: PublicKey::from_secret_key(&secp, seckey),
+            signature: secp.sign(&message, seckey),
+        });
+    }
+
+    /// Checks that the invoice is signed by the receiver with the given
+    /// public key, which must be known to the payer in advance. The public
+    /// key embedded into the invoice is not trusted, since anyone is able to
+    /// re-sign modified invoice with own key.
+    pub fn verify_signature(&self, pubkey: &PublicKey) -> Result<(), Error> {
+        let sig = self.signature.as_ref().ok_or(Error::NoSignature)?;
+        if sig.pubkey != *pubkey {
+            Err(Error::InvalidSignature)?
+        }
+        let secp = secp256k1::Secp256k1::verification_only();
+        let message = secp256k1::Message::from_slice(&self.signature_hash()[..])
+            .expect("SHA256 hash is always a valid signature message");
+        secp.verify(&message, &sig.signature, pubkey)
+            .map_err(|_| Error::InvalidSignature)
+    }
+
+    /// Validation performed by the payer before paying the invoice: checks
+    /// expiration, network and, if the receiver public key is known to the
+    /// payer, the signature
+    pub fn validate(
+        &self,
+        network: &bp::Network,
+        receiver: Option<&PublicKey>,
+    ) -> Result<(), Error> {
+        if let Some(expiry) = self.expiry {
+            if expiry <= Utc::now().naive_utc() {
+                Err(Error::Expired)?
+            }
+        }
+        if let Some(ref invoice_network) = self.network {
+            if invoice_network != network {
+                Err(Error::NetworkMismatch)?
+            }
+        }
+        if let Outpoint::Address(ref address) = self.outpoint {
+            if bp::Network::from(address.network) != *network {
+                Err(Error::NetworkMismatch)?
+            }
+        }
+        if let Some(pubkey) = receiver {
+            self.verify_signature(pubkey)?;
+        }
+        Ok(())
+    }
+
+    /// Compact bech32 representation of the invoice, suitable for QR codes
+    pub fn to_bech32_string(&self) -> Result<String, Error> {
+        let data = strict_encode(self).map_err(|_| Error::WrongCompactEncoding)?;
+        bech32::encode(RGB20_BECH32_HRP_INVOICE_COMPACT, data.to_base32())
+            .map_err(|_| Error::WrongBech32Encoding)
+    }
+
+    /// Parses invoice from its compact bech32 representation
+    pub fn from_bech32_str(s: &str) -> Result<Self, Error> {
+        let (hrp, data) = bech32::decode(s).map_err(|_| Error::WrongBech32Encoding)?;
+        if hrp != RGB20_BECH32_HRP_INVOICE_COMPACT {
+            Err(Error::WrongBech32Encoding)?
+        }
+        let data = Vec::<u8>::from_base32(&data).map_err(|_| Error::WrongBech32Encoding)?;
+        strict_decode(&data).map_err(|_| Error::WrongCompactEncoding)
+    }
+
+    fn url(&self, signed: bool) -> Url {
+        let mut url =
+            Url::parse(&format!("rgb20:{}", self.outpoint)).expect("Internal Url generation error");
+        {
+            let mut query = url.query_pairs_mut();
+            query
+                .append_pair("asset", &self.contract_id.to_bech32().to_string())
+                .append_pair("amount", &self.amount.to_string());
+            if let Some(ref network) = self.network {
+                query.append_pair("network", &network.to_string());
+            }
+            if let Some(expiry) = self.expiry {
+                query.append_pair("expiry", &expiry.timestamp().to_string());
+            }
+            if let Some(ref description) = self.description {
+                query.append_pair("description", description);
+            }
+            if let Some(id) = self.id {
+                query.append_pair("id", &id.to_string());
+            }
+            if let (true, Some(sig)) = (signed, &self.signature) {
+                query
+                    .append_pair("pubkey", &sig.pubkey.to_string())
+                    .append_pair("signature", &sig.signature.serialize_compact().to_hex());
+            }
+        }
+        url
+    }
+}
+
+impl StrictEncode for Outpoint {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        Ok(match self {
+            Outpoint::BlindedUtxo(outpoint_hash) => strict_encode_list!(e; 0u8, outpoint_hash),
+            Outpoint::Address(address) => strict_encode_list!(e; 1u8, address.to_string()),
+        })
+    }
+}
+
+impl StrictDecode for Outpoint {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        match u8::strict_decode(&mut d)? {
+            0 => Ok(Outpoint::BlindedUtxo(OutpointHash::strict_decode(&mut d)?)),
+            1 => Ok(Outpoint::Address(
+                String::strict_decode(&mut d)?.parse().map_err(|_| {
+                    strict_encoding::Error::DataIntegrityError("Wrong bitcoin address".to_string())
+                })?,
+            )),
+            code => Err(strict_encoding::Error::DataIntegrityError(format!(
+                "Unknown invoice outpoint type {}",
+                code
+            ))),
+        }
+    }
+}
+
+impl StrictEncode for InvoiceSignature {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        let pubkey = self.pubkey.serialize();
+        let signature = self.signature.serialize_compact();
+        e.write_all(&pubkey)?;
+        e.write_all(&signature)?;
+        Ok(pubkey.len() + signature.len())
+    }
+}
+
+impl StrictDecode for InvoiceSignature {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        let mut pubkey = [0u8; secp256k1::constants::PUBLIC_KEY_SIZE];
+        let mut signature = [0u8; secp256k1::constants::COMPACT_SIGNATURE_SIZE];
+        d.read_exact(&mut pubkey)?;
+        d.read_exact(&mut signature)?;
+        Ok(Self {
+            pubkey: PublicKey::from_slice(&pubkey).map_err(|_| {
+                strict_encoding::Error::DataIntegrityError("Wrong invoice public key".to_string())
+            })?,
+            signature: Signature::from_compact(&signature).map_err(|_| {
+                strict_encoding::Error::DataIntegrityError("Wrong invoice signature".to_string())
+            })?,
+        })
+    }
+}
+
+impl StrictEncode for Invoice {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        // Dates have no strict encoding, so we use unix timestamps instead
+        Ok(strict_encode_list!(e;
+            self.contract_id,
+            self.outpoint,
+            self.amount,
+            self.expiry.map(|expiry| expiry.timestamp()),
+            self.network,
+            self.description,
+            self.id,
+            self.signature
+        ))
+    }
+}
+
+impl StrictDecode for Invoice {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            contract_id: ContractId::strict_decode(&mut d)?,
+            outpoint: Outpoint::strict_decode(&mut d)?,
+            amount: AccountingAmount::strict_decode(&mut d)?,
+            expiry: Option::<i64>::strict_decode(&mut d)?
+                .map(|timestamp| {
+                    NaiveDateTime::from_timestamp_opt(timestamp, 0).ok_or(
+                        strict_encoding::Error::DataIntegrityError(format!(
+                            "Invoice expiry {} is out of range",
+                            timestamp
+                        )),
+                    )
+                })
+                .transpose()?,
+            network: Option::<bp::Network>::strict_decode(&mut d)?,
+            description: Option::<String>::strict_decode(&mut d)?,
+            id: Option::<u64>::strict_decode(&mut d)?,
+            signature: Option::<InvoiceSignature>::strict_decode(&mut d)?,
+        })
+    }
 }
 
 impl From<OutpointDescriptor> for Outpoint {
@@ -98,6 +385,12 @@ impl FromStr for Invoice {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.to_lowercase()
+            .starts_with(&format!("{}1", RGB20_BECH32_HRP_INVOICE_COMPACT))
+        {
+            return Invoice::from_bech32_str(s);
+        }
+
         let url = Url::parse(s)?;
         if url.scheme() != "rgb20" {
             return Err(Error::WrongUrlScheme);
@@ -106,20 +399,54 @@ impl FromStr for Invoice {
             return Err(Error::NonNullAuthority);
         }
         let outpoint = url.path().parse()?;
-        let (_, amount) = url
-            .query_pairs()
-            .find(|(x, _)| x == "amount")
-            .ok_or(Error::NoAmount)?;
-        let amount = amount.parse().map_err(|_| Error::WrongAmountEncoding)?;
-        let (_, contract_id) = url
-            .query_pairs()
-            .find(|(x, _)| x == "asset")
-            .ok_or(Error::NoAsset)?;
-        let contract_id = contract_id.parse().map_err(|_| Error::WrongAssetEncoding)?;
+        let param = |name: &str| {
+            url.query_pairs()
+                .find(|(x, _)| x == name)
+                .map(|(_, value)| value.into_owned())
+        };
+        let amount = param("amount")
+            .ok_or(Error::NoAmount)?
+            .parse()
+            .map_err(|_| Error::WrongAmountEncoding)?;
+        let contract_id = param("asset")
+            .ok_or(Error::NoAsset)?
+            .parse()
+            .map_err(|_| Error::WrongAssetEncoding)?;
+        let network = param("network")
+            .map(|network| network.parse().map_err(|_| Error::WrongNetworkEncoding))
+            .transpose()?;
+        let expiry = param("expiry")
+            .map(|expiry| {
+                expiry
+                    .parse()
+                    .ok()
+                    .and_then(|timestamp| NaiveDateTime::from_timestamp_opt(timestamp, 0))
+                    .ok_or(Error::WrongExpiryEncoding)
+            })
+            .transpose()?;
+        let id = param("id")
+            .map(|id| id.parse().map_err(|_| Error::WrongIdEncoding))
+            .transpose()?;
+        let signature = match (param("pubkey"), param("signature")) {
+            (None, None) => None,
+            (Some(pubkey), Some(signature)) => Some(InvoiceSignature {
+                pubkey: pubkey.parse().map_err(|_| Error::WrongPubkeyEncoding)?,
+                signature: Vec::<u8>::from_hex(&signature)
+                    .ok()
+                    .and_then(|data| Signature::from_compact(&data).ok())
+                    .ok_or(Error::WrongSignatureEncoding)?,
+            }),
+            _ => Err(Error::IncompleteSignature)?,
+        };
         Ok(Invoice {
             contract_id,
             outpoint,
             amount,
+            expiry,
+            network,
+            description: param("description"),
+            id,
+            signature,
         })
     }
 }
@@ -144,13 +471,177 @@ impl Display for Outpoint {
 
 impl Display for Invoice {
     fn fmt(&self, f: &mut Formatter<'_>) -> ::core::fmt::Result {
-        let url = Url::parse(&format!(
-            "rgb20:{}?asset={}&amount={}",
-            self.outpoint,
-            self.contract_id.to_bech32(),
-            self.amount
-        ))
-        .expect("Internal Url generation error");
-        write!(f, "{}", url)
+        write!(f, "{}", self.url(true))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn testnet() -> bp::Network {
+        bp::Network::from(bitcoin::Network::Testnet)
+    }
+
+    fn seckey(no: u8) -> SecretKey {
+        SecretKey::from_slice(&[no; 32]).unwrap()
+    }
+
+    fn pubkey(no: u8) -> PublicKey {
+        PublicKey::from_secret_key(&secp256k1::Secp256k1::signing_only(), &seckey(no))
+    }
+
+    fn invoice() -> Invoice {
+        let mut invoice = Invoice::new(
+            ContractId::from_inner([1; 32]),
+            Outpoint::BlindedUtxo(OutpointHash::from_inner([2; 32])),
+            AccountingAmount::from(100),
+        );
+        invoice.expiry = Some(NaiveDateTime::from_timestamp(4_000_000_000, 0));
+        invoice.network = Some(testnet());
+        invoice.description = Some("Coffee & cake".to_string());
+        invoice.id = Some(42);
+        invoice
+    }
+
+    fn address_invoice(address: &str) -> Invoice {
+        Invoice::new(
+            ContractId::from_inner([1; 32]),
+            Outpoint::Address(address.parse().unwrap()),
+            AccountingAmount::from(100),
+        )
+    }
+
+    #[test]
+    fn url_round_trip() {
+        let mut invoice = invoice();
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice.clone()));
+
+        invoice.sign(&seckey(1));
+        let s = invoice.to_string();
+        assert!(s.starts_with("rgb20:"));
+        assert_eq!(Invoice::from_str(&s), Ok(invoice));
+
+        let invoice = address_invoice("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx");
+        assert_eq!(Invoice::from_str(&invoice.to_string()), Ok(invoice));
+    }
+
+    #[test]
+    fn url_errors() {
+        let s = invoice().to_string();
+        assert_eq!(
+            Invoice::from_str(&s.replacen("rgb20:", "rgb21:", 1)),
+            Err(Error::WrongUrlScheme)
+        );
+        assert_eq!(
+            Invoice::from_str(&s.replacen("amount=100", "amount=x", 1)),
+            Err(Error::WrongAmountEncoding)
+        );
+
+        let mut invoice = invoice();
+        invoice.sign(&seckey(1));
+        let s = invoice.to_string();
+        let unsigned = &s[..s.find("&signature=").unwrap()];
+        assert_eq!(Invoice::from_str(unsigned), Err(Error::IncompleteSignature));
+    }
+
+    #[test]
+    fn bech32_round_trip() {
+        let mut invoice = invoice();
+        invoice.sign(&seckey(1));
+        let s = invoice.to_bech32_string().unwrap();
+        assert!(s.starts_with(&format!("{}1", RGB20_BECH32_HRP_INVOICE_COMPACT)));
+        assert_eq!(Invoice::from_bech32_str(&s), Ok(invoice.clone()));
+        assert_eq!(Invoice::from_str(&s), Ok(invoice.clone()));
+        assert_eq!(Invoice::from_str(&s.to_uppercase()), Ok(invoice));
+
+        let invoice = address_invoice("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx");
+        let s = invoice.to_bech32_string().unwrap();
+        assert_eq!(Invoice::from_bech32_str(&s), Ok(invoice));
+
+        let foreign = bech32::encode("bc", vec![0u8; 8].to_base32()).unwrap();
+        assert_eq!(
+            Invoice::from_bech32_str(&foreign),
+            Err(Error::WrongBech32Encoding)
+        );
+    }
+
+    #[test]
+    fn signature() {
+        let mut invoice = invoice();
+        assert_eq!(
+            invoice.verify_signature(&pubkey(1)),
+            Err(Error::NoSignature)
+        );
+
+        invoice.sign(&seckey(1));
+        assert_eq!(invoice.verify_signature(&pubkey(1)), Ok(()));
+        assert_eq!(
+            invoice.verify_signature(&pubkey(2)),
+            Err(Error::InvalidSignature)
+        );
+
+        let mut tampered = invoice.clone();
+        tampered.amount = AccountingAmount::from(1_000);
+        assert_eq!(
+            tampered.verify_signature(&pubkey(1)),
+            Err(Error::InvalidSignature)
+        );
+
+        // Re-signing with another key must not pass as the receiver's one
+        let mut resigned = tampered.clone();
+        resigned.sign(&seckey(2));
+        assert_eq!(
+            resigned.verify_signature(&pubkey(1)),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let mut invoice = invoice();
+        assert_eq!(invoice.validate(&testnet(), None), Ok(()));
+        assert_eq!(
+            invoice.validate(&testnet(), Some(&pubkey(1))),
+            Err(Error::NoSignature)
+        );
+        invoice.sign(&seckey(1));
+        assert_eq!(invoice.validate(&testnet(), Some(&pubkey(1))), Ok(()));
+        assert_eq!(
+            invoice.validate(&testnet(), Some(&pubkey(2))),
+            Err(Error::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let mut invoice = invoice();
+        invoice.expiry = Some(NaiveDateTime::from_timestamp(1_500_000_000, 0));
+        assert_eq!(invoice.validate(&testnet(), None), Err(Error::Expired));
+    }
+
+    #[test]
+    fn wrong_network() {
+        let mainnet = bp::Network::from(bitcoin::Network::Bitcoin);
+
+        let invoice = invoice();
+        assert_eq!(
+            invoice.validate(&mainnet, None),
+            Err(Error::NetworkMismatch)
+        );
+
+        let invoice = address_invoice("tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx");
+        assert_eq!(invoice.validate(&testnet(), None), Ok(()));
+        assert_eq!(
+            invoice.validate(&mainnet, None),
+            Err(Error::NetworkMismatch)
+        );
+
+        let invoice = address_invoice("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4");
+        assert_eq!(invoice.validate(&mainnet, None), Ok(()));
+        assert_eq!(
+            invoice.validate(&testnet(), None),
+            Err(Error::NetworkMismatch)
+        );
     }
 }
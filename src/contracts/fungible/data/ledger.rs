@@ -0,0 +1,160 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use chrono::NaiveDateTime;
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use lnpbp::bitcoin::{OutPoint, Txid};
+use lnpbp::bp::blind::OutpointHash;
+use lnpbp::rgb::{Amount, NodeId};
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+
+use crate::error::ParseError;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LedgerOperation {
+    /// Assets received to a locally-controlled outpoint, including transfer
+    /// change
+    Incoming,
+
+    /// Assets sent to the other party
+    Outgoing,
+
+    /// Locally-controlled allocation spent by one of our transfers
+    Spending,
+}
+
+impl fmt::Display for LedgerOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerOperation::Incoming => f.write_str("incoming"),
+            LedgerOperation::Outgoing => f.write_str("outgoing"),
+            LedgerOperation::Spending => f.write_str("spending"),
+        }
+    }
+}
+
+impl FromStr for LedgerOperation {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "incoming" => Ok(LedgerOperation::Incoming),
+            "outgoing" => Ok(LedgerOperation::Outgoing),
+            "spending" => Ok(LedgerOperation::Spending),
+            _ => Err(ParseError),
+        }
+    }
+}
+
+impl StrictEncode for LedgerOperation {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        let code: u8 = match self {
+            LedgerOperation::Incoming => 0,
+            LedgerOperation::Outgoing => 1,
+            LedgerOperation::Spending => 2,
+        };
+        code.strict_encode(e)
+    }
+}
+
+impl StrictDecode for LedgerOperation {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        match u8::strict_decode(d)? {
+            0 => Ok(LedgerOperation::Incoming),
+            1 => Ok(LedgerOperation::Outgoing),
+            2 => Ok(LedgerOperation::Spending),
+            code => Err(strict_encoding::Error::DataIntegrityError(format!(
+                "Unknown ledger operation {}",
+                code
+            ))),
+        }
+    }
+}
+
+/// Record of the asset movement, kept for wallet reconciliation. Unlike the
+/// known allocations, ledger entries are never removed.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Display)]
+#[display_from(Debug)]
+pub struct LedgerEntry {
+    pub operation: LedgerOperation,
+    /// State transition which has created (for incoming and outgoing
+    /// operations) or spent (for spendings) the allocation
+    pub node_id: NodeId,
+    /// Witness transaction of the state transition
+    pub txid: Txid,
+    /// Locally-controlled outpoint receiving or spending the assets; unknown
+    /// for outgoing operations
+    pub outpoint: Option<OutPoint>,
+    /// Amount in atomic units
+    pub amount: Amount,
+    /// Blinded seal of the other party for outgoing operations
+    pub counterparty: Option<OutpointHash>,
+    pub timestamp: NaiveDateTime,
+    /// Entries of our transfers remain pending until the spent outpoints are
+    /// forgotten (i.e. the witness transaction is mined); pending transfer
+    /// may be cancelled, releasing its inputs
+    #[serde(default)]
+    pub pending: bool,
+}
+
+impl StrictEncode for LedgerEntry {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, mut e: E) -> Result<usize, Self::Error> {
+        // Dates have no strict encoding, so we use unix timestamps instead
+        Ok(strict_encode_list!(e;
+            self.operation,
+            self.node_id,
+            self.txid,
+            self.outpoint,
+            self.amount,
+            self.counterparty,
+            self.timestamp.timestamp(),
+            self.pending
+        ))
+    }
+}
+
+impl StrictDecode for LedgerEntry {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
+        Ok(Self {
+            operation: LedgerOperation::strict_decode(&mut d)?,
+            node_id: NodeId::strict_decode(&mut d)?,
+            txid: Txid::strict_decode(&mut d)?,
+            outpoint: Option::<OutPoint>::strict_decode(&mut d)?,
+            amount: Amount::strict_decode(&mut d)?,
+            counterparty: Option::<OutpointHash>::strict_decode(&mut d)?,
+            timestamp: {
+                let timestamp = i64::strict_decode(&mut d)?;
+                NaiveDateTime::from_timestamp_opt(timestamp, 0).ok_or(
+                    strict_encoding::Error::DataIntegrityError(format!(
+                        "Ledger entry timestamp {} is out of range",
+                        timestamp
+                    )),
+                )?
+            },
+            pending: bool::strict_decode(&mut d)?,
+        })
+    }
+}
@@ -11,12 +11,16 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
+mod accounting;
 mod asset;
 mod invoice;
+mod ledger;
 mod outcoins;
 pub mod schema;
 
-pub use asset::{Allocation, Asset, Coins, Issue, Supply};
-pub use invoice::{Invoice, Outpoint, OutpointDescriptor};
+pub use accounting::{AccountingAmount, AmountError};
+pub use asset::{Allocation, Asset, Coins, Issue, PruneRight, Supply};
+pub use invoice::{Error as InvoiceError, Invoice, InvoiceSignature, Outpoint, OutpointDescriptor};
+pub use ledger::{LedgerEntry, LedgerOperation};
 pub use outcoins::{Outcoincealed, Outcoins};
 pub use schema::SchemaError;
@@ -25,12 +25,13 @@ use lnpbp::bp;
 use lnpbp::rgb::SealDefinition;
 use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
 
+use super::AccountingAmount;
 use crate::error::ParseError;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Display)]
 #[display_from(Debug)]
 pub struct Outcoins {
-    pub coins: f32,
+    pub coins: AccountingAmount,
     pub vout: u16,
     pub txid: Option<Txid>,
 }
@@ -38,7 +39,7 @@ pub struct Outcoins {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Display)]
 #[display_from(Debug)]
 pub struct Outcoincealed {
-    pub coins: f32,
+    pub coins: AccountingAmount,
     pub seal_confidential: OutpointHash,
 }
 
@@ -74,7 +75,7 @@ impl StrictDecode for Outcoins {
 
     fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
         Ok(Self {
-            coins: f32::strict_decode(&mut d)?,
+            coins: AccountingAmount::strict_decode(&mut d)?,
             vout: u16::strict_decode(&mut d)?,
             txid: Option::<Txid>::strict_decode(&mut d)?,
         })
@@ -94,7 +95,7 @@ impl StrictDecode for Outcoincealed {
 
     fn strict_decode<D: io::Read>(mut d: D) -> Result<Self, Self::Error> {
         Ok(Self {
-            coins: f32::strict_decode(&mut d)?,
+            coins: AccountingAmount::strict_decode(&mut d)?,
             seal_confidential: OutpointHash::strict_decode(&mut d)?,
         })
     }
@@ -105,7 +106,7 @@ impl FromStr for Outcoins {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let re = Regex::new(
             r"(?x)
-                ^(?P<coins>[\d.,_']+) # float amount
+                ^(?P<coins>[\d._']+) # decimal amount
                 @
                 ((?P<txid>[a-f\d]{64}) # Txid
                 :)
@@ -138,7 +139,7 @@ impl FromStr for Outcoincealed {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let re = Regex::new(
             r"(?x)
-                ^(?P<coins>[\d.,_']+) # float amount
+                ^(?P<coins>[\d._']+) # decimal amount
                 @
                 ((?P<seal>[a-f\d]{64}))$ # Confidential seal: outpoint hash
             ",
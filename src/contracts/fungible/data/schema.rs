@@ -15,11 +15,16 @@ use core::ops::Neg;
 use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::ToPrimitive;
 
+use lnpbp::bitcoin::hashes::Hash;
+use lnpbp::bitcoin::Txid;
+use lnpbp::bp::blind::OutpointReveal;
 use lnpbp::rgb::schema::{
     script, AssignmentAction, Bits, DataFormat, DiscreteFiniteFieldFormat, GenesisSchema,
     Occurences, Schema, StateFormat, StateSchema, TransitionSchema,
 };
 
+use lnpbp::rgb::seal;
+
 use crate::error::ServiceErrorDomain;
 use crate::type_map;
 
@@ -30,6 +35,10 @@ pub enum SchemaError {
     NotAllFieldsPresent,
 
     WrongSchemaId,
+
+    WrongTransitionType,
+
+    TotalSupplyExceeded,
 }
 
 impl From<SchemaError> for ServiceErrorDomain {
@@ -177,6 +186,19 @@ pub fn schema() -> Schema {
     }
 }
 
+/// Seal receiving the burned part of the assets closed by a prune transition.
+/// It points to the transaction with zero id, which can't be spent, and has
+/// zero blinding, so anyone can verify the burned amount. Since confidential
+/// amount validation requires the transition to be balanced, the burned
+/// amount must be assigned to this seal.
+pub fn burn_seal() -> seal::Revealed {
+    seal::Revealed::TxOutpoint(OutpointReveal {
+        blinding: 0,
+        txid: Txid::from_inner([0u8; 32]),
+        vout: 0,
+    })
+}
+
 impl Neg for FieldType {
     type Output = usize;
 
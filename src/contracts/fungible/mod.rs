@@ -15,12 +15,14 @@ mod config;
 mod data;
 mod processor;
 mod runtime;
+mod selection;
 
 pub(self) mod cache;
 
 pub use data::{
-    schema, Allocation, Asset, Coins, Invoice, Issue, Outcoincealed, Outcoins, Outpoint,
-    OutpointDescriptor, SchemaError, Supply,
+    schema, AccountingAmount, Allocation, AmountError, Asset, Coins, Invoice, InvoiceError,
+    InvoiceSignature, Issue, LedgerEntry, LedgerOperation, Outcoincealed, Outcoins, Outpoint,
+    OutpointDescriptor, PruneRight, SchemaError, Supply,
 };
 
 pub use config::{Config, Opts};
@@ -29,3 +31,4 @@ pub use runtime::{main_with_config, Runtime};
 pub use cache::CacheError;
 pub use processor::IssueStructure;
 pub(self) use processor::Processor;
+pub use selection::{SelectionError, SelectionStrategy};
@@ -23,7 +23,8 @@ use lnpbp::rgb::prelude::*;
 use bitcoin::OutPoint;
 
 use super::schema::{self, AssignmentsType, FieldType, TransitionType};
-use super::{Allocation, Asset, Coins, Outcoincealed, Outcoins};
+use super::{AccountingAmount, Allocation, AmountError, Asset, Coins, Outcoincealed, Outcoins};
+use super::{SelectionError, SelectionStrategy};
 
 use crate::error::{BootstrapError, ServiceErrorDomain};
 use crate::util::SealSpec;
@@ -35,7 +36,7 @@ pub struct Processor {}
 pub enum IssueStructure {
     SingleIssue,
     MultipleIssues {
-        max_supply: f32,
+        max_supply: AccountingAmount,
         reissue_control: SealSpec,
     },
 }
@@ -89,11 +90,13 @@ impl Processor {
         let allocations = allocations
             .into_iter()
             .map(|outcoins| {
-                let amount = Coins::transmutate(outcoins.coins, precision);
-                issued_supply += amount;
-                (outcoins.seal_definition(), amount)
+                let amount = Coins::transmutate(outcoins.coins, precision)?;
+                issued_supply = issued_supply
+                    .checked_add(amount)
+                    .ok_or(AmountError::Overflow)?;
+                Ok((outcoins.seal_definition(), amount))
             })
-            .collect();
+            .collect::<Result<_, AmountError>>()?;
         let mut assignments = BTreeMap::new();
         assignments.insert(
             -AssignmentsType::Assets,
@@ -107,7 +110,7 @@ impl Processor {
             reissue_control,
         } = issue_structure
         {
-            total_supply = Coins::transmutate(max_supply, precision);
+            total_supply = Coins::transmutate(max_supply, precision)?;
             if total_supply < issued_supply {
                 Err(ServiceErrorDomain::Schema(format!(
                     "Total supply ({}) should be greater than the issued supply ({})",
@@ -153,6 +156,241 @@ impl Processor {
         Ok((asset, genesis))
     }
 
+    /// Creates secondary issuance (inflation) state transition closing the
+    /// currently unspent issue control seal of the asset. The new supply is
+    /// allocated to the provided seals and can't make known circulating
+    /// supply exceed the total supply of the asset. If `next_issue` is given,
+    /// the issue control right is re-assigned to it; otherwise no further
+    /// issuance will be possible.
+    pub fn inflate(
+        &mut self,
+        asset: &Asset,
+        allocations: Vec<Outcoins>,
+        next_issue: Option<SealSpec>,
+    ) -> Result<Transition, ServiceErrorDomain> {
+        if asset.unspent_issue_txo().is_none() {
+            Err(ServiceErrorDomain::Schema(
+                "Asset issue control right is unknown or already spent".to_string(),
+            ))?
+        }
+        let last_issue = asset
+            .known_issues()
+            .iter()
+            .filter_map(|issues| issues.back())
+            .last()
+            .ok_or(ServiceErrorDomain::Schema(
+                "No known issues for the asset".to_string(),
+            ))?;
+
+        let precision = *asset.fractional_bits();
+        let mut issued_supply = 0u64;
+        let allocations = allocations
+            .into_iter()
+            .map(|outcoins| {
+                let amount = Coins::transmutate(outcoins.coins, precision)?;
+                issued_supply = issued_supply
+                    .checked_add(amount)
+                    .ok_or(AmountError::Overflow)?;
+                Ok((outcoins.seal_definition(), amount))
+            })
+            .collect::<Result<_, AmountError>>()?;
+
+        let circulating = asset.supply().known_circulating.sats();
+        let total = asset
+            .supply()
+            .total
+            .as_ref()
+            .map(Coins::sats)
+            .unwrap_or(core::u64::MAX);
+        if circulating
+            .checked_add(issued_supply)
+            .map(|supply| supply > total)
+            .unwrap_or(true)
+        {
+            Err(ServiceErrorDomain::Schema(format!(
+                "Issued supply ({}) together with the circulating supply ({}) exceeds total supply ({})",
+                issued_supply, circulating, total
+            )))?
+        }
+
+        let metadata = type_map! {
+            FieldType::IssuedSupply => field!(U64, issued_supply)
+        };
+
+        let mut assignments = BTreeMap::new();
+        assignments.insert(
+            -AssignmentsType::Assets,
+            AssignmentsVariant::zero_balanced(vec![], allocations, vec![]),
+        );
+        if let Some(seal_spec) = next_issue {
+            assignments.insert(
+                -AssignmentsType::Issue,
+                AssignmentsVariant::Declarative(bset![Assignment::Revealed {
+                    seal_definition: seal_spec.seal_definition(),
+                    assigned_state: data::Void
+                }]),
+            );
+        }
+
+        // Genesis and issue transitions may have only a single issue control
+        // assignment, so it always has zero index
+        let mut ancestors = Ancestors::new();
+        ancestors.insert(
+            last_issue.id,
+            bmap! { -AssignmentsType::Issue => vec![0u16] },
+        );
+
+        let transition = Transition::with(
+            -TransitionType::Issue,
+            metadata.into(),
+            ancestors,
+            assignments,
+            vec![],
+        );
+
+        Ok(transition)
+    }
+
+    /// Creates prune (burn) state transition closing the prune right assigned
+    /// to `prune_right` output together with all asset allocations under the
+    /// `inputs` outputs. Assets which are not burned are re-allocated to the
+    /// `remainder` seals; the rest of the input amount is assigned to the
+    /// unspendable `schema::burn_seal`. If
+    /// `next_prune` is given, the prune right is re-assigned to it. Optional
+    /// `proof` data are recorded into the transition `PruneProof` metadata.
+    pub fn prune(
+        &mut self,
+        asset: &Asset,
+        prune_right: OutPoint,
+        inputs: Vec<OutPoint>,
+        remainder: Vec<Outcoins>,
+        next_prune: Option<SealSpec>,
+        proof: Option<Vec<u8>>,
+    ) -> Result<Transition, ServiceErrorDomain> {
+        let right = asset
+            .prune_right(&prune_right)
+            .ok_or(format!("No known prune right at {}", prune_right))?;
+        if inputs.is_empty() {
+            Err("Prune transition must close at least one asset allocation".to_string())?
+        }
+
+        // Collecting all allocations to prune
+        let mut input_allocations = Vec::<Allocation>::new();
+        let spent = asset.spent_outpoints();
+        for seal in &inputs {
+            if spent.contains(seal) {
+                Err(format!(
+                    "Input {} is already spent by a pending transfer",
+                    seal
+                ))?
+            }
+            let found = asset
+                .allocations(seal)
+                .ok_or(format!("Unknown input {}", seal))?
+                .clone();
+            if found.len() == 0 {
+                Err(format!("Unknown input {}", seal))?
+            }
+            input_allocations.extend(found);
+        }
+        let total_inputs = input_allocations
+            .iter()
+            .try_fold(0u64, |acc, alloc| acc.checked_add(alloc.amount.amount))
+            .ok_or(AmountError::Overflow)?;
+
+        let mut total_outputs = 0u64;
+        let mut allocations_remainder: Vec<_> = remainder
+            .into_iter()
+            .map(|outcoins| {
+                let amount = Coins::transmutate(outcoins.coins, *asset.fractional_bits())?;
+                total_outputs = total_outputs
+                    .checked_add(amount)
+                    .ok_or(AmountError::Overflow)?;
+                Ok((outcoins.seal_definition(), amount))
+            })
+            .collect::<Result<_, AmountError>>()?;
+        if total_inputs < total_outputs {
+            Err("Input amount is lower than the remaining amount".to_string())?
+        }
+        let burned = total_inputs - total_outputs;
+        if burned > 0 {
+            allocations_remainder.push((schema::burn_seal(), burned));
+        }
+
+        let mut metadata = type_map! {};
+        if let Some(proof) = proof {
+            metadata.insert(-FieldType::PruneProof, field!(Bytes, proof));
+        }
+
+        let mut assignments = BTreeMap::new();
+        if !allocations_remainder.is_empty() {
+            let input_amounts = input_allocations
+                .iter()
+                .map(|alloc| alloc.amount.clone())
+                .collect();
+            assignments.insert(
+                -AssignmentsType::Assets,
+                AssignmentsVariant::zero_balanced(input_amounts, allocations_remainder, vec![]),
+            );
+        }
+        if let Some(seal_spec) = next_prune {
+            assignments.insert(
+                -AssignmentsType::Prune,
+                AssignmentsVariant::Declarative(bset![Assignment::Revealed {
+                    seal_definition: seal_spec.seal_definition(),
+                    assigned_state: data::Void
+                }]),
+            );
+        }
+
+        let mut ancestors = Ancestors::new();
+        ancestors
+            .entry(right.node_id)
+            .or_insert(bmap! {})
+            .insert(-AssignmentsType::Prune, vec![right.index]);
+        for alloc in input_allocations {
+            ancestors
+                .entry(alloc.node_id)
+                .or_insert(bmap! {})
+                .entry(-AssignmentsType::Assets)
+                .or_insert(vec![])
+                .push(alloc.index);
+        }
+
+        let transition = Transition::with(
+            -TransitionType::Prune,
+            metadata.into(),
+            ancestors,
+            assignments,
+            vec![],
+        );
+
+        Ok(transition)
+    }
+
+    /// Selects asset inputs sufficient to cover all the provided outputs using
+    /// the given coin selection strategy
+    pub fn select_inputs(
+        &self,
+        asset: &Asset,
+        outputs: Vec<AccountingAmount>,
+        strategy: SelectionStrategy,
+    ) -> Result<Vec<OutPoint>, ServiceErrorDomain> {
+        let precision = *asset.fractional_bits();
+        let required = outputs.into_iter().try_fold(0u64, |sum, coins| {
+            sum.checked_add(Coins::transmutate(coins, precision)?)
+                .ok_or(AmountError::Overflow)
+        })?;
+        let inputs = strategy.select(asset, required)?;
+        debug!(
+            "Selected {} input(s) for the amount {} using {} strategy",
+            inputs.len(),
+            required,
+            strategy
+        );
+        Ok(inputs)
+    }
+
     /// Function creates a fungible asset-specific state transition (i.e. RGB-20
     /// schema-based) given an asset information, inputs and desired outputs
     pub fn transfer(
@@ -161,6 +399,7 @@ impl Processor {
         inputs: Vec<OutPoint>,
         ours: Vec<Outcoins>,
         theirs: Vec<Outcoincealed>,
+        theirs_witness: Vec<(seal::Revealed, AccountingAmount)>,
         change_outpoint: Option<bp::blind::OutpointHash>,
     ) -> Result<Transition, ServiceErrorDomain> {
         // Collecting all input allocations
@@ -178,39 +417,63 @@ impl Processor {
         // Computing sum of inputs
         let total_inputs = input_allocations
             .iter()
-            .fold(0u64, |acc, alloc| acc + alloc.amount.amount);
+            .try_fold(0u64, |acc, alloc| acc.checked_add(alloc.amount.amount))
+            .ok_or(AmountError::Overflow)?;
 
         let metadata = type_map! {};
-        let mut total_outputs = 0;
-        let allocations_ours = ours
+        let mut total_outputs = 0u64;
+        // Receiver's seals on the witness transaction outputs are not
+        // blinded, so they are allocated in the same way as our own seals
+        let allocations_ours: Vec<(seal::Revealed, u64)> = ours
             .into_iter()
-            .map(|outcoins| {
-                let amount = Coins::transmutate(outcoins.coins, *asset.fractional_bits());
-                total_outputs += amount;
-                (outcoins.seal_definition(), amount)
+            .map(|outcoins| (outcoins.seal_definition(), outcoins.coins))
+            .chain(theirs_witness)
+            .map(|(seal_definition, coins)| {
+                let amount = Coins::transmutate(coins, *asset.fractional_bits())?;
+                total_outputs = total_outputs
+                    .checked_add(amount)
+                    .ok_or(AmountError::Overflow)?;
+                Ok((seal_definition, amount))
             })
-            .collect();
+            .collect::<Result<_, AmountError>>()?;
         let mut allocations_theirs: Vec<(bp::blind::OutpointHash, u64)> = theirs
             .into_iter()
             .map(|outcoincealed| {
-                let amount = Coins::transmutate(outcoincealed.coins, *asset.fractional_bits());
-                total_outputs += amount;
-                (outcoincealed.seal_confidential, amount)
+                let amount = Coins::transmutate(outcoincealed.coins, *asset.fractional_bits())?;
+                total_outputs = total_outputs
+                    .checked_add(amount)
+                    .ok_or(AmountError::Overflow)?;
+                Ok((outcoincealed.seal_confidential, amount))
             })
-            .collect();
+            .collect::<Result<_, AmountError>>()?;
 
         if total_inputs < total_outputs {
-            Err("Input amount is lower than output amount".to_string())?
+            Err(SelectionError::InsufficientFunds {
+                required: total_outputs,
+                available: total_inputs,
+            })?
         } else if total_inputs > total_outputs {
-            // TODO: keep in mind the dust limit here
-
-            if change_outpoint.is_none() {
-                Err("Excess input with no change".to_string())?
+            if let Some(change_outpoint) = change_outpoint {
+                allocations_theirs.push((change_outpoint, total_inputs - total_outputs));
             } else {
-                allocations_theirs.push((change_outpoint.unwrap(), total_inputs - total_outputs));
+                Err("Excess input with no change".to_string())?
             }
         }
 
+        // Each of the outputs, including change, must not be below dust limit
+        let dust_limit = asset.dust_limit().sats();
+        if let Some(amount) = allocations_ours
+            .iter()
+            .map(|(_, amount)| amount)
+            .chain(allocations_theirs.iter().map(|(_, amount)| amount))
+            .find(|amount| **amount < dust_limit)
+        {
+            Err(format!(
+                "Output amount {} is below the asset dust limit {}",
+                amount, dust_limit
+            ))?
+        }
+
         let input_amounts = input_allocations
             .iter()
             .map(|alloc| alloc.amount.clone())
@@ -241,3 +504,198 @@ impl Processor {
         Ok(transition)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::bitcoin::hashes::Hash;
+    use lnpbp::bitcoin::Txid;
+
+    fn outpoint(no: u8) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_inner([no; 32]),
+            vout: no as u32,
+        }
+    }
+
+    fn outcoins(no: u8, coins: u64) -> Outcoins {
+        Outcoins {
+            coins: AccountingAmount::from(coins),
+            vout: no as u16,
+            txid: Some(Txid::from_inner([no; 32])),
+        }
+    }
+
+    // Issues asset with 70 and 30 units allocated to outpoints 1 and 2 and
+    // prune right assigned to outpoint 3
+    fn issue() -> (Asset, Genesis) {
+        Processor::new()
+            .unwrap()
+            .issue(
+                bp::Network::from(bitcoin::Network::Testnet),
+                "TST".to_string(),
+                "Test asset".to_string(),
+                None,
+                IssueStructure::SingleIssue,
+                vec![outcoins(1, 70), outcoins(2, 30)],
+                0,
+                vec![SealSpec {
+                    vout: 3,
+                    txid: Some(Txid::from_inner([3; 32])),
+                }],
+                None,
+            )
+            .unwrap()
+    }
+
+    fn assert_valid(genesis: &Genesis, transition: &Transition) {
+        let mut nodes = BTreeMap::<NodeId, &dyn Node>::new();
+        nodes.insert(genesis.node_id(), genesis);
+        let status = schema::schema().validate(&nodes, transition);
+        assert!(
+            status.failures.is_empty(),
+            "Prune transition fails schema validation: {:?}",
+            status.failures
+        );
+    }
+
+    fn burned_amount(transition: &Transition) -> Amount {
+        transition
+            .assignments_by_type(-AssignmentsType::Assets)
+            .into_iter()
+            .flat_map(|variant| match variant {
+                AssignmentsVariant::DiscreteFiniteField(set) => set.clone(),
+                _ => bset![],
+            })
+            .filter_map(|assignment| match assignment {
+                Assignment::Revealed {
+                    seal_definition,
+                    assigned_state,
+                } if seal_definition == schema::burn_seal() => Some(assigned_state.amount),
+                _ => None,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn prune_without_inputs() {
+        let (asset, _) = issue();
+        assert!(Processor::new()
+            .unwrap()
+            .prune(
+                &asset,
+                outpoint(3),
+                vec![],
+                vec![outcoins(4, 0)],
+                None,
+                None
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn prune_reallocating() {
+        let (mut asset, genesis) = issue();
+        let transition = Processor::new()
+            .unwrap()
+            .prune(
+                &asset,
+                outpoint(3),
+                vec![outpoint(1)],
+                vec![outcoins(4, 70)],
+                None,
+                None,
+            )
+            .unwrap();
+        assert_valid(&genesis, &transition);
+        assert_eq!(burned_amount(&transition), 0);
+
+        let supply = asset
+            .add_prune(&transition, Txid::from_inner([5; 32]))
+            .unwrap();
+        assert_eq!(supply.known_circulating.sats(), 100);
+        assert!(asset.allocations(&outpoint(1)).is_none());
+        assert!(asset.prune_right(&outpoint(3)).is_none());
+    }
+
+    #[test]
+    fn prune_burning() {
+        let (mut asset, genesis) = issue();
+        let transition = Processor::new()
+            .unwrap()
+            .prune(
+                &asset,
+                outpoint(3),
+                vec![outpoint(1)],
+                vec![outcoins(4, 50)],
+                None,
+                Some(b"burn proof".to_vec()),
+            )
+            .unwrap();
+        assert_valid(&genesis, &transition);
+        assert_eq!(burned_amount(&transition), 20);
+
+        let supply = asset
+            .add_prune(&transition, Txid::from_inner([5; 32]))
+            .unwrap();
+        assert_eq!(supply.known_circulating.sats(), 80);
+        assert_eq!(asset.allocations(&outpoint(4)).map(Vec::len), Some(1));
+        assert_eq!(asset.unspent_allocations().values().sum::<Amount>(), 80);
+    }
+
+    #[test]
+    fn prune_burning_everything() {
+        let (mut asset, genesis) = issue();
+        let transition = Processor::new()
+            .unwrap()
+            .prune(
+                &asset,
+                outpoint(3),
+                vec![outpoint(1), outpoint(2)],
+                vec![],
+                None,
+                None,
+            )
+            .unwrap();
+        assert_valid(&genesis, &transition);
+        assert_eq!(burned_amount(&transition), 100);
+
+        let supply = asset
+            .add_prune(&transition, Txid::from_inner([5; 32]))
+            .unwrap();
+        assert_eq!(supply.known_circulating.sats(), 0);
+        assert!(asset.unspent_allocations().is_empty());
+    }
+
+    #[test]
+    fn transfer_spent_input() {
+        let (mut asset, _) = issue();
+        asset.record_outgoing(
+            NodeId::from_inner([9; 32]),
+            Txid::from_inner([9; 32]),
+            &[outpoint(1)],
+            vec![],
+        );
+        let mut processor = Processor::new().unwrap();
+        assert!(processor
+            .transfer(
+                &mut asset,
+                vec![outpoint(1)],
+                vec![outcoins(5, 70)],
+                vec![],
+                vec![],
+                None
+            )
+            .is_err());
+        assert!(processor
+            .transfer(
+                &mut asset,
+                vec![outpoint(2)],
+                vec![outcoins(5, 30)],
+                vec![],
+                vec![],
+                None
+            )
+            .is_ok());
+    }
+}
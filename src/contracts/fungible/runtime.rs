@@ -15,21 +15,27 @@ use ::core::borrow::Borrow;
 use ::core::convert::TryFrom;
 use ::std::path::PathBuf;
 
-use lnpbp::bitcoin::OutPoint;
+use lnpbp::bitcoin::util::psbt;
+use lnpbp::bitcoin::{OutPoint, Script, TxIn};
 use lnpbp::client_side_validation::Conceal;
 use lnpbp::lnp::presentation::Encode;
 use lnpbp::lnp::zmq::ApiType;
 use lnpbp::lnp::{transport, NoEncryption, Session, Unmarshall, Unmarshaller};
-use lnpbp::rgb::{AssignmentsVariant, Consignment, ContractId, Genesis, Node};
+use lnpbp::rgb::{
+    seal, Assignment, AssignmentsVariant, Consignment, ContractId, Genesis, Node, NodeId,
+};
 use lnpbp::TryService;
 
-use super::cache::{Cache, FileCache, FileCacheConfig};
+use super::cache::{Cache, CacheError, FileCache, FileCacheConfig, SqlCache, SqlCacheConfig};
 use super::schema::AssignmentsType;
-use super::{schema, Asset, Config, IssueStructure, Processor};
+use super::{schema, AmountError, Asset, Coins, Config, IssueStructure, Outpoint, Processor};
 use crate::api::stash::MergeRequest;
 use crate::api::{
     self,
-    fungible::{AcceptApi, Issue, Request, TransferApi},
+    fungible::{
+        AcceptApi, ConsignmentAccepted, InflateApi, Issue, Notification, PruneApi, Request,
+        TransferApi, TransferCreated,
+    },
     reply,
     stash::ConsignRequest,
     Reply,
@@ -38,6 +44,7 @@ use crate::error::{
     ApiErrorType, BootstrapError, RuntimeError, ServiceError, ServiceErrorDomain,
     ServiceErrorSource,
 };
+use crate::util::RevealStore;
 
 pub struct Runtime {
     /// Original configuration object
@@ -52,12 +59,17 @@ pub struct Runtime {
     /// Stash RPC client session
     stash_rpc: Session<NoEncryption, transport::zmq::Connection>,
 
-    /// Publish-subscribe API socket
+    /// Stash publish-subscribe session receiving stash update notifications
     stash_sub: Session<NoEncryption, transport::zmq::Connection>,
 
     /// RGB fungible assets data cache: relational database sharing the client-
-    /// friendly asset information with clients
-    cacher: FileCache,
+    /// friendly asset information with clients. The exact backend is selected
+    /// by the cache connection string
+    cacher: Box<dyn Cache<Error = CacheError> + Send>,
+
+    /// Invoice store: blinding data for the outpoints blinded by the node,
+    /// used to accept incoming transfers
+    reveals: RevealStore,
 
     /// Processor instance: handles business logic outside of stash scope
     processor: Processor,
@@ -67,27 +79,52 @@ pub struct Runtime {
 
     /// Unmarshaller instance used for parsing RPC request
     reply_unmarshaller: Unmarshaller<Reply>,
+
+    /// Unmarshaller instance used for parsing stash notifications
+    stash_unmarshaller: Unmarshaller<api::stash::Notification>,
 }
 
 impl Runtime {
     /// Internal function for avoiding index-implementation specific function
-    /// use and reduce number of errors. Cacher may be switched with compile
-    /// configuration options and, thus, we need to make sure that the structure
+    /// use and reduce number of errors. Cacher is switched with cache
+    /// connection string and, thus, we need to make sure that the structure
     /// we use corresponds to certain trait and not specific type.
-    fn cache(&self) -> &impl Cache {
-        &self.cacher
+    fn cache(&self) -> &dyn Cache<Error = CacheError> {
+        self.cacher.as_ref()
     }
 
     pub fn init(config: Config, mut context: &mut zmq::Context) -> Result<Self, BootstrapError> {
         let processor = Processor::new()?;
 
-        let cacher = FileCache::new(FileCacheConfig {
-            data_dir: PathBuf::from(&config.cache),
-            data_format: config.format,
-        })
-        .map_err(|err| {
+        let cacher: Box<dyn Cache<Error = CacheError> + Send> =
+            if SqlCacheConfig::is_sql_connection(&config.cache) {
+                Box::new(
+                    SqlCache::new(SqlCacheConfig {
+                        connection: config.cache.clone(),
+                        data_format: config.format,
+                    })
+                    .map_err(|err| {
+                        error!("{}", err);
+                        err
+                    })?,
+                )
+            } else {
+                Box::new(
+                    FileCache::new(FileCacheConfig {
+                        data_dir: PathBuf::from(&config.cache),
+                        data_format: config.format,
+                        journal: config.journal,
+                    })
+                    .map_err(|err| {
+                        error!("{}", err);
+                        err
+                    })?,
+                )
+            };
+
+        let reveals = RevealStore::open(config.invoices.clone()).map_err(|err| {
             error!("{}", err);
-            err
+            BootstrapError::StorageError
         })?;
 
         let session_rpc = Session::new_zmq_unencrypted(
@@ -125,9 +162,11 @@ impl Runtime {
             stash_rpc,
             stash_sub,
             cacher,
+            reveals,
             processor,
             unmarshaller: Request::create_unmarshaller(),
             reply_unmarshaller: Reply::create_unmarshaller(),
+            stash_unmarshaller: api::stash::Notification::create_unmarshaller(),
         })
     }
 }
@@ -143,6 +182,12 @@ impl TryService for Runtime {
             RuntimeError::Internal("Unable to register RGB20 schema".to_string())
         })?;
 
+        debug!("Checking stash daemon network");
+        self.check_stash_network().await.map_err(|err| {
+            error!("Stash daemon network does not match: {}", err);
+            RuntimeError::Internal(err.to_string())
+        })?;
+
         loop {
             match self.run().await {
                 Ok(_) => debug!("API request processing complete"),
@@ -157,7 +202,21 @@ impl TryService for Runtime {
 
 impl Runtime {
     async fn run(&mut self) -> Result<(), RuntimeError> {
-        trace!("Awaiting for ZMQ RPC requests...");
+        trace!("Awaiting for ZMQ RPC requests and stash notifications...");
+        let mut poll_items = [
+            self.session_rpc.as_socket().as_poll_item(zmq::POLLIN),
+            self.stash_sub.as_socket().as_poll_item(zmq::POLLIN),
+        ];
+        zmq::poll(&mut poll_items, -1).map_err(|err| RuntimeError::zmq_reply("rpc", err))?;
+        let (rpc_ready, stash_ready) = (poll_items[0].is_readable(), poll_items[1].is_readable());
+
+        if stash_ready {
+            self.stash_notification().await?;
+        }
+        if !rpc_ready {
+            return Ok(());
+        }
+
         let raw = self.session_rpc.recv_raw_message()?;
         let reply = self.rpc_process(raw).await.unwrap_or_else(|err| err);
         trace!("Preparing ZMQ RPC reply: {:?}", reply);
@@ -170,6 +229,48 @@ impl Runtime {
         Ok(())
     }
 
+    async fn stash_notification(&mut self) -> Result<(), RuntimeError> {
+        let raw = self.stash_sub.recv_raw_message()?;
+        let notification = match self.stash_unmarshaller.unmarshall(&raw) {
+            Ok(notification) => notification,
+            Err(err) => {
+                warn!("Unable to parse stash notification: {}", err);
+                return Ok(());
+            }
+        };
+        debug!("Received stash notification: {}", notification);
+        match &*notification {
+            api::stash::Notification::GenesisAdded(contract_id) => {
+                if let Err(err) = self.sync_genesis(*contract_id).await {
+                    warn!("Unable to cache asset {}: {}", contract_id, err);
+                }
+            }
+            api::stash::Notification::ConsignmentMerged(contract_id) => {
+                trace!("Stash has merged consignment for {}", contract_id);
+            }
+            api::stash::Notification::Forgotten(forgotten) => {
+                trace!(
+                    "Stash has forgotten {} transitions",
+                    forgotten.transitions.len()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Publishes cache update over the PUB socket. Since the update is
+    /// already persisted, failures are logged and not reported to the client
+    fn notify(&mut self, notification: Notification) {
+        trace!("Publishing ZMQ notification: {:?}", notification);
+        let result = notification
+            .encode()
+            .map_err(RuntimeError::from)
+            .and_then(|data| Ok(self.session_pub.send_raw_message(data)?));
+        if let Err(err) = result {
+            error!("Unable to publish notification {}: {}", notification, err);
+        }
+    }
+
     async fn rpc_process(&mut self, raw: Vec<u8>) -> Result<Reply, Reply> {
         trace!("Got {} bytes over ZMQ RPC: {:?}", raw.len(), raw);
         let message = &*self
@@ -185,6 +286,12 @@ impl Runtime {
             Request::Forget(outpoint) => self.rpc_forget(outpoint).await,
             Request::ImportAsset(genesis) => self.rpc_import_asset(genesis).await,
             Request::ExportAsset(asset_id) => self.rpc_export_asset(asset_id).await,
+            Request::Inflate(inflate) => self.rpc_inflate(inflate).await,
+            Request::Prune(prune) => self.rpc_prune(prune).await,
+            Request::Blind(outpoint) => self.rpc_blind(outpoint).await,
+            Request::Balance(contract_id) => self.rpc_balance(contract_id).await,
+            Request::History(contract_id) => self.rpc_history(contract_id).await,
+            Request::CancelTransfer(node_id) => self.rpc_cancel_transfer(node_id).await,
             Request::Sync => self.rpc_sync().await,
         }
         .map_err(|err| ServiceError::contract(err, "fungible"))?)
@@ -214,63 +321,301 @@ impl Runtime {
             issue_structure,
             issue.allocate.clone(),
             issue.precision,
-            vec![],
+            issue.prune.clone(),
             issue.dust_limit,
         )?;
 
-        self.import_asset(asset, genesis).await?;
-
-        // TODO: Send push request to client informing about cache update
+        let contract_id = genesis.contract_id();
+        self.import_asset(asset.clone(), genesis.clone()).await?;
+        self.notify(Notification::AssetIssued(contract_id));
 
-        Ok(Reply::Success)
+        Ok(Reply::Issue(reply::Issue { asset, genesis }))
     }
 
     async fn rpc_transfer(&mut self, transfer: &TransferApi) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got TRANSFER {}", transfer);
 
-        // TODO: Check inputs that they really exist and have sufficient amount of
-        //       asset for the transfer operation
-
+        ServiceErrorDomain::check_network(&self.config.network, &transfer.network)?;
         let mut asset = self.cacher.asset(transfer.contract_id)?.clone();
+        ServiceErrorDomain::check_network(&self.config.network, &asset.network())?;
+
+        // Seals for the receiver's witness transaction outputs are generated
+        // here, since we need to keep them revealed in the consignment
+        let theirs_witness = transfer
+            .theirs_witness
+            .iter()
+            .map(|outcoins| (outcoins.seal_definition(), outcoins.coins))
+            .collect::<Vec<_>>();
+        let mut outpoints = transfer
+            .theirs
+            .iter()
+            .map(|o| o.seal_confidential)
+            .chain(transfer.change)
+            .collect::<Vec<_>>();
+        outpoints.extend(theirs_witness.iter().map(|(seal, _)| seal.conceal()));
+
+        // Amounts sent to the other party, recorded into the ledger
+        let sent = transfer
+            .theirs
+            .iter()
+            .map(|o| (o.seal_confidential, o.coins))
+            .chain(
+                theirs_witness
+                    .iter()
+                    .map(|(seal, coins)| (seal.conceal(), *coins)),
+            )
+            .map(|(seal_confidential, coins)| {
+                Ok((
+                    seal_confidential,
+                    Coins::transmutate(coins, *asset.fractional_bits())?,
+                ))
+            })
+            .collect::<Result<Vec<_>, AmountError>>()?;
+
+        let mut psbt = transfer.psbt.clone();
+        let inputs = if transfer.inputs.is_empty() {
+            let outputs = transfer
+                .ours
+                .iter()
+                .chain(&transfer.theirs_witness)
+                .map(|outcoins| outcoins.coins)
+                .chain(
+                    transfer
+                        .theirs
+                        .iter()
+                        .map(|outcoincealed| outcoincealed.coins),
+                )
+                .collect();
+            let inputs = self
+                .processor
+                .select_inputs(&asset, outputs, transfer.selection)?;
+            // Witness transaction must spend all the selected outputs; the
+            // wallet is responsible for providing the rest of input data
+            // required for signing
+            for outpoint in &inputs {
+                if !psbt
+                    .global
+                    .unsigned_tx
+                    .input
+                    .iter()
+                    .any(|txin| txin.previous_output == *outpoint)
+                {
+                    psbt.global.unsigned_tx.input.push(TxIn {
+                        previous_output: *outpoint,
+                        script_sig: Script::new(),
+                        sequence: core::u32::MAX,
+                        witness: vec![],
+                    });
+                    psbt.inputs.push(psbt::Input::default());
+                }
+            }
+            inputs
+        } else {
+            transfer.inputs.clone()
+        };
 
+        // Processor checks that all inputs are known and have sufficient
+        // amount of asset, so insufficient transfers never reach the stash
         let transition = self.processor.transfer(
             &mut asset,
-            transfer.inputs.clone(),
+            inputs.clone(),
             transfer.ours.clone(),
             transfer.theirs.clone(),
+            theirs_witness,
             transfer.change.clone(),
         )?;
 
         let reply = self
             .consign(ConsignRequest {
                 contract_id: transfer.contract_id,
-                inputs: transfer.inputs.clone(),
-                transition,
+                inputs: inputs.clone(),
+                transition: transition.clone(),
                 // TODO: Collect blank state transitions and pass it here
                 other_transition_ids: bmap![],
-                outpoints: transfer
-                    .theirs
-                    .iter()
-                    .map(|o| (o.seal_confidential))
-                    .collect(),
-                psbt: transfer.psbt.clone(),
+                outpoints,
+                psbt,
+            })
+            .await?;
+
+        if let Reply::Transfer(ref consigned) = reply {
+            let witness_txid = consigned.psbt.global.unsigned_tx.txid();
+            asset.record_outgoing(transition.node_id(), witness_txid, &inputs, sent);
+            self.cacher.add_asset(asset)?;
+            self.notify(Notification::TransferCreated(TransferCreated {
+                contract_id: transfer.contract_id,
+                node_id: transition.node_id(),
+                txid: witness_txid,
+            }));
+        }
+
+        Ok(reply)
+    }
+
+    async fn rpc_inflate(&mut self, inflate: &InflateApi) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got INFLATE {}", inflate);
+
+        ServiceErrorDomain::check_network(&self.config.network, &inflate.network)?;
+        let mut asset = self.cacher.asset(inflate.contract_id)?.clone();
+        ServiceErrorDomain::check_network(&self.config.network, &asset.network())?;
+        let issue_txo = (*asset.unspent_issue_txo()).ok_or(ServiceErrorDomain::Schema(
+            "Asset issue control right is unknown or already spent".to_string(),
+        ))?;
+
+        let transition =
+            self.processor
+                .inflate(&asset, inflate.allocate.clone(), inflate.next_issue.clone())?;
+
+        // All seals defined by the issue are known to us and must remain
+        // revealed in the consignment
+        let outpoints = [-AssignmentsType::Assets, -AssignmentsType::Issue]
+            .iter()
+            .flat_map(|t| transition.known_seal_definitions_by_type(*t))
+            .map(|seal| seal.conceal())
+            .collect();
+
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: inflate.contract_id,
+                inputs: vec![issue_txo],
+                transition: transition.clone(),
+                other_transition_ids: bmap![],
+                outpoints,
+                psbt: inflate.psbt.clone(),
             })
             .await?;
 
+        if let Reply::Transfer(ref transfer) = reply {
+            let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+            let supply = asset.add_issue(&transition, witness_txid)?;
+            debug!("Asset supply after inflation: {}", supply);
+            self.cacher.add_asset(asset)?;
+            self.notify(Notification::TransferCreated(TransferCreated {
+                contract_id: inflate.contract_id,
+                node_id: transition.node_id(),
+                txid: witness_txid,
+            }));
+        }
+
         Ok(reply)
     }
 
+    async fn rpc_prune(&mut self, prune: &PruneApi) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got PRUNE {}", prune);
+
+        ServiceErrorDomain::check_network(&self.config.network, &prune.network)?;
+        let mut asset = self.cacher.asset(prune.contract_id)?.clone();
+        ServiceErrorDomain::check_network(&self.config.network, &asset.network())?;
+
+        let transition = self.processor.prune(
+            &asset,
+            prune.prune_right,
+            prune.inputs.clone(),
+            prune.remainder.clone(),
+            prune.next_prune.clone(),
+            prune.proof.clone(),
+        )?;
+
+        // All seals defined by the prune transition are known to us and must
+        // remain revealed in the consignment
+        let outpoints = [-AssignmentsType::Assets, -AssignmentsType::Prune]
+            .iter()
+            .flat_map(|t| transition.known_seal_definitions_by_type(*t))
+            .map(|seal| seal.conceal())
+            .collect();
+
+        let mut inputs = vec![prune.prune_right];
+        inputs.extend(
+            prune
+                .inputs
+                .iter()
+                .filter(|input| **input != prune.prune_right),
+        );
+
+        let reply = self
+            .consign(ConsignRequest {
+                contract_id: prune.contract_id,
+                inputs,
+                transition: transition.clone(),
+                other_transition_ids: bmap![],
+                outpoints,
+                psbt: prune.psbt.clone(),
+            })
+            .await?;
+
+        if let Reply::Transfer(ref transfer) = reply {
+            let witness_txid = transfer.psbt.global.unsigned_tx.txid();
+            let supply = asset.add_prune(&transition, witness_txid)?;
+            debug!("Asset supply after pruning: {}", supply);
+            self.cacher.add_asset(asset)?;
+            self.notify(Notification::TransferCreated(TransferCreated {
+                contract_id: prune.contract_id,
+                node_id: transition.node_id(),
+                txid: witness_txid,
+            }));
+        }
+
+        Ok(reply)
+    }
+
+    async fn rpc_blind(&mut self, outpoint: &OutPoint) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BLIND {}", outpoint);
+        let reveal = self.reveals.blind(*outpoint)?;
+        Ok(Reply::BlindedOutpoint(reveal.conceal()))
+    }
+
+    async fn rpc_balance(&mut self, contract_id: &ContractId) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got BALANCE {}", contract_id);
+        let asset = self.cacher.asset(*contract_id)?;
+        let allocations = asset.unspent_allocations();
+        Ok(Reply::Balance(reply::Balance {
+            contract_id: *contract_id,
+            available: allocations
+                .values()
+                .fold(0u64, |sum, amount| sum.saturating_add(*amount)),
+            spent: asset.spent_amount(),
+            allocations,
+        }))
+    }
+
+    async fn rpc_history(&mut self, contract_id: &ContractId) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got HISTORY {}", contract_id);
+        let asset = self.cacher.asset(*contract_id)?;
+        Ok(Reply::History(asset.known_ledger().clone()))
+    }
+
+    async fn rpc_cancel_transfer(&mut self, node_id: &NodeId) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got CANCEL_TRANSFER {}", node_id);
+        let mut assets = self
+            .cacher
+            .assets()?
+            .into_iter()
+            .map(Clone::clone)
+            .collect::<Vec<_>>();
+        for asset in &mut assets {
+            if asset.cancel_transfer(*node_id) {
+                self.cacher.add_asset(asset.clone())?;
+                self.notify(Notification::TransferCancelled(*node_id));
+                return Ok(Reply::Success);
+            }
+        }
+        Ok(Reply::Nothing)
+    }
+
     async fn rpc_validate(
         &mut self,
         consignment: &Consignment,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got VALIDATE");
-        self.validate(consignment.clone()).await?;
-        Ok(Reply::Success)
+        ServiceErrorDomain::check_network(&self.config.network, &consignment.genesis.network())?;
+        Ok(self.validate(consignment.clone()).await?)
     }
 
     async fn rpc_accept(&mut self, accept: &AcceptApi) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got ACCEPT");
+        ServiceErrorDomain::check_network(
+            &self.config.network,
+            &accept.consignment.genesis.network(),
+        )?;
         Ok(self.accept(accept.clone()).await?)
     }
 
@@ -287,8 +632,10 @@ impl Runtime {
 
     async fn rpc_import_asset(&mut self, genesis: &Genesis) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got IMPORT_ASSET");
+        ServiceErrorDomain::check_network(&self.config.network, &genesis.network())?;
         self.import_asset(Asset::try_from(genesis.clone())?, genesis.clone())
             .await?;
+        self.notify(Notification::AssetImported(genesis.contract_id()));
         Ok(Reply::Success)
     }
 
@@ -325,6 +672,22 @@ impl Runtime {
         }
     }
 
+    /// Adds asset to the cache if its genesis was added to the stash by
+    /// some other client; geneses of other schemata are ignored
+    async fn sync_genesis(&mut self, contract_id: ContractId) -> Result<(), ServiceErrorDomain> {
+        if self.cacher.has_asset(contract_id)? {
+            return Ok(());
+        }
+        let genesis = self.export_asset(contract_id).await?;
+        if genesis.schema_id() != schema::schema().schema_id() {
+            return Ok(());
+        }
+        ServiceErrorDomain::check_network(&self.config.network, &genesis.network())?;
+        self.cacher.add_asset(Asset::try_from(genesis)?)?;
+        self.notify(Notification::AssetImported(contract_id));
+        Ok(())
+    }
+
     async fn export_asset(&mut self, asset_id: ContractId) -> Result<Genesis, ServiceErrorDomain> {
         match self
             .stash_req_rep(api::stash::Request::ReadGenesis(asset_id))
@@ -335,6 +698,15 @@ impl Runtime {
         }
     }
 
+    async fn check_stash_network(&mut self) -> Result<(), ServiceErrorDomain> {
+        match self.stash_req_rep(api::stash::Request::Network()).await? {
+            Reply::Network(network) => {
+                ServiceErrorDomain::check_network(&self.config.network, &network)
+            }
+            _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
+        }
+    }
+
     async fn consign(&mut self, consign_req: ConsignRequest) -> Result<Reply, ServiceErrorDomain> {
         let reply = self
             .stash_req_rep(api::stash::Request::Consign(consign_req))
@@ -352,12 +724,26 @@ impl Runtime {
             .await?;
 
         match reply {
-            Reply::Success | Reply::Failure(_) => Ok(reply),
+            Reply::ValidationStatus(_) | Reply::Failure(_) => Ok(reply),
             _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
         }
     }
 
-    async fn accept(&mut self, accept: AcceptApi) -> Result<Reply, ServiceErrorDomain> {
+    async fn accept(&mut self, mut accept: AcceptApi) -> Result<Reply, ServiceErrorDomain> {
+        // Looking up reveals for the consignment endpoints blinded by us
+        for (_, outpoint_hash) in &accept.consignment.endpoints {
+            if accept
+                .reveal_outpoints
+                .iter()
+                .any(|reveal| reveal.conceal() == *outpoint_hash)
+            {
+                continue;
+            }
+            if let Some(reveal) = self.reveals.reveal(outpoint_hash) {
+                accept.reveal_outpoints.push(reveal);
+            }
+        }
+
         let reply = self
             .stash_req_rep(api::stash::Request::Merge(MergeRequest {
                 consignment: accept.consignment.clone(),
@@ -369,38 +755,101 @@ impl Runtime {
             let mut asset = if self.cacher.has_asset(asset_id)? {
                 self.cacher.asset(asset_id)?.clone()
             } else {
-                Asset::try_from(accept.consignment.genesis)?
+                Asset::try_from(accept.consignment.genesis.clone())?
             };
 
-            for (_, transition) in &accept.consignment.data {
+            // Every endpoint of the consignment matching our seals is
+            // credited, including several allocations assigned to the same
+            // seal by a batch payment
+            let mut credited = Vec::<NodeId>::new();
+            for (anchor, transition) in &accept.consignment.data {
+                let node_id = transition.node_id();
+                let endpoints = accept
+                    .consignment
+                    .endpoints
+                    .iter()
+                    .filter(|(id, _)| *id == node_id)
+                    .map(|(_, outpoint_hash)| *outpoint_hash)
+                    .collect::<Vec<_>>();
+                if endpoints.is_empty() {
+                    continue;
+                }
                 let set = transition.assignments_by_type(-AssignmentsType::Assets);
                 for variant in set {
                     if let AssignmentsVariant::DiscreteFiniteField(set) = variant {
                         for (index, assignment) in set.into_iter().enumerate() {
-                            if let Some(seal) = accept.reveal_outpoints.iter().find(|op| {
-                                op.conceal() == assignment.seal_definition_confidential()
-                            }) {
-                                if let Some(assigned_state) = assignment.assigned_state() {
-                                    asset.add_allocation(
-                                        seal.clone().into(),
-                                        transition.node_id(),
-                                        index as u16,
-                                        assigned_state.clone(),
+                            let seal_confidential = assignment.seal_definition_confidential();
+                            if !endpoints.contains(&seal_confidential) {
+                                continue;
+                            }
+                            let outpoint = if let Some(seal) = accept
+                                .reveal_outpoints
+                                .iter()
+                                .find(|op| op.conceal() == seal_confidential)
+                            {
+                                seal.clone().into()
+                            } else if let Assignment::Revealed {
+                                seal_definition: seal::Revealed::WitnessVout { vout, .. },
+                                ..
+                            } = assignment
+                            {
+                                // Seals on the witness transaction outputs
+                                // are used for paying to address invoices
+                                // and are revealed to the receiver; still
+                                // the output must pay to our address
+                                let outpoint = OutPoint {
+                                    txid: anchor.txid,
+                                    vout: *vout as u32,
+                                };
+                                if !is_invoiced_output(&accept, asset_id, outpoint) {
+                                    warn!(
+                                        "Witness output {} does not pay to any of the invoiced addresses; ignoring it",
+                                        outpoint
                                     );
-                                } else {
-                                    Err(ServiceErrorDomain::Internal(
-                                        "Consignment structure is broken".to_string(),
-                                    ))?
+                                    continue;
                                 }
+                                outpoint
+                            } else {
+                                continue;
+                            };
+                            if let Some(assigned_state) = assignment.assigned_state() {
+                                asset.add_allocation(
+                                    outpoint,
+                                    node_id,
+                                    index as u16,
+                                    assigned_state.clone(),
+                                );
+                                asset.record_incoming(
+                                    node_id,
+                                    anchor.txid,
+                                    outpoint,
+                                    assigned_state.amount,
+                                );
+                                credited.push(node_id);
+                            } else {
+                                Err(ServiceErrorDomain::Internal(
+                                    "Consignment structure is broken".to_string(),
+                                ))?
                             }
                         }
                     }
                 }
             }
+            if credited.is_empty() {
+                warn!("None of the consignment endpoints are controlled by us");
+            } else {
+                debug!("Accepted {} asset allocations", credited.len());
+            }
 
             self.cacher.add_asset(asset)?;
+            credited.dedup();
+            self.notify(Notification::ConsignmentAccepted(ConsignmentAccepted {
+                contract_id: asset_id,
+                node_ids: credited,
+            }));
             Ok(reply)
-        } else if let Reply::Failure(_) = &reply {
+        } else if let Reply::Failure(_) | Reply::ValidationStatus(_) = &reply {
+            // Consignment was rejected by the stash, so we do not update cache
             Ok(reply)
         } else {
             Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply))
@@ -417,11 +866,11 @@ impl Runtime {
             .collect::<Vec<_>>();
         for asset in assets {
             let mut asset = asset.clone();
-            for allocation in asset
-                .clone()
-                .allocations(&outpoint)
-                .ok_or(ServiceErrorDomain::Cache)?
-            {
+            // Outpoint is spent on-chain, so our transfer spending it can't be
+            // cancelled anymore
+            asset.confirm_spending(outpoint);
+            // Assets having no allocations on the outpoint are left intact
+            for allocation in asset.clone().allocations(&outpoint).unwrap_or(&vec![]) {
                 asset.remove_allocation(
                     outpoint,
                     allocation.node_id,
@@ -439,9 +888,10 @@ impl Runtime {
         let reply = self
             .stash_req_rep(api::stash::Request::Forget(removal_list))
             .await?;
+        self.notify(Notification::AllocationForgotten(outpoint));
 
         match reply {
-            Reply::Success | Reply::Failure(_) => Ok(reply),
+            Reply::Forgotten(_) | Reply::Success | Reply::Failure(_) => Ok(reply),
             _ => Err(ServiceErrorDomain::Api(ApiErrorType::UnexpectedReply)),
         }
     }
@@ -467,3 +917,26 @@ pub async fn main_with_config(config: Config) -> Result<(), BootstrapError> {
     let runtime = Runtime::init(config, &mut context)?;
     runtime.run_or_panic("Fungible contract runtime").await
 }
+
+/// Checks whether the witness transaction output pays to an address from one
+/// of the paying to address invoices for the given asset
+fn is_invoiced_output(accept: &AcceptApi, asset_id: ContractId, outpoint: OutPoint) -> bool {
+    let script_pubkey = match accept
+        .witness_txs
+        .iter()
+        .find(|tx| tx.txid() == outpoint.txid)
+        .and_then(|tx| tx.output.get(outpoint.vout as usize))
+    {
+        Some(txout) => &txout.script_pubkey,
+        None => return false,
+    };
+    accept
+        .invoices
+        .iter()
+        .any(|invoice| match invoice.outpoint {
+            Outpoint::Address(ref address) => {
+                invoice.contract_id == asset_id && address.script_pubkey() == *script_pubkey
+            }
+            _ => false,
+        })
+}
@@ -0,0 +1,480 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+//! Automatic selection of asset inputs (coin selection) for the transfers.
+//! Since closing a seal spends all asset allocations assigned to it, the
+//! selection operates on transaction outputs with the total asset amount
+//! allocated to each of them.
+
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use lnpbp::bitcoin::OutPoint;
+use lnpbp::rgb::Amount;
+use lnpbp::strict_encoding::{self, StrictDecode, StrictEncode};
+
+use super::Asset;
+use crate::error::{ParseError, ServiceErrorDomain};
+
+#[derive(Clone, PartialEq, Eq, Debug, Display, Error)]
+#[display_from(Debug)]
+pub enum SelectionError {
+    /// Known unspent asset allocations are not sufficient to cover the
+    /// required amount
+    InsufficientFunds { required: Amount, available: Amount },
+}
+
+impl From<SelectionError> for ServiceErrorDomain {
+    fn from(err: SelectionError) -> Self {
+        match err {
+            SelectionError::InsufficientFunds {
+                required,
+                available,
+            } => ServiceErrorDomain::InsufficientFunds {
+                required,
+                available,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum SelectionStrategy {
+    /// Spends outputs with the largest asset amounts first
+    LargestFirst,
+
+    /// Spends the least possible number of outputs, preferring the ones
+    /// leaving the smallest change
+    MinimizeInputs,
+
+    /// Spends outputs in random order, dropping the ones which are not
+    /// required to cover the amount; this prevents wallet fingerprinting by
+    /// the selection order and avoids linking unnecessary outputs together
+    Privacy,
+}
+
+impl Default for SelectionStrategy {
+    fn default() -> Self {
+        SelectionStrategy::LargestFirst
+    }
+}
+
+impl fmt::Display for SelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionStrategy::LargestFirst => f.write_str("largest-first"),
+            SelectionStrategy::MinimizeInputs => f.write_str("minimize-inputs"),
+            SelectionStrategy::Privacy => f.write_str("privacy"),
+        }
+    }
+}
+
+impl FromStr for SelectionStrategy {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "largest-first" | "largest" => Ok(SelectionStrategy::LargestFirst),
+            "minimize-inputs" | "minimize" => Ok(SelectionStrategy::MinimizeInputs),
+            "privacy" => Ok(SelectionStrategy::Privacy),
+            _ => Err(ParseError),
+        }
+    }
+}
+
+impl StrictEncode for SelectionStrategy {
+    type Error = strict_encoding::Error;
+
+    fn strict_encode<E: io::Write>(&self, e: E) -> Result<usize, Self::Error> {
+        let code: u8 = match self {
+            SelectionStrategy::LargestFirst => 0,
+            SelectionStrategy::MinimizeInputs => 1,
+            SelectionStrategy::Privacy => 2,
+        };
+        code.strict_encode(e)
+    }
+}
+
+impl StrictDecode for SelectionStrategy {
+    type Error = strict_encoding::Error;
+
+    fn strict_decode<D: io::Read>(d: D) -> Result<Self, Self::Error> {
+        match u8::strict_decode(d)? {
+            0 => Ok(SelectionStrategy::LargestFirst),
+            1 => Ok(SelectionStrategy::MinimizeInputs),
+            2 => Ok(SelectionStrategy::Privacy),
+            code => Err(strict_encoding::Error::DataIntegrityError(format!(
+                "Unknown coin selection strategy {}",
+                code
+            ))),
+        }
+    }
+}
+
+impl SelectionStrategy {
+    /// Selects transaction outputs with the unspent asset allocations, which
+    /// together hold either exactly `amount` of the asset (in atomic units)
+    /// or enough to leave a change which is not below the asset dust limit
+    pub fn select(self, asset: &Asset, amount: Amount) -> Result<Vec<OutPoint>, SelectionError> {
+        if amount == 0 {
+            return Ok(vec![]);
+        }
+        let target = Target {
+            amount,
+            dust_limit: asset.dust_limit().sats(),
+        };
+
+        let mut candidates = asset
+            .unspent_allocations()
+            .into_iter()
+            .filter(|(_, value)| *value > 0)
+            .collect::<Vec<(OutPoint, Amount)>>();
+
+        let available = candidates
+            .iter()
+            .fold(0u64, |sum, (_, value)| sum.saturating_add(*value));
+        if available < amount {
+            Err(SelectionError::InsufficientFunds {
+                required: amount,
+                available,
+            })?
+        }
+
+        // Largest values go first; ties are resolved by outpoint to keep the
+        // selection deterministic
+        candidates.sort_by(|(outpoint1, value1), (outpoint2, value2)| {
+            value2.cmp(value1).then(outpoint1.cmp(outpoint2))
+        });
+
+        let selected = match self {
+            SelectionStrategy::LargestFirst => accumulate(&candidates, target),
+            SelectionStrategy::MinimizeInputs => {
+                let largest = accumulate(&candidates, target);
+                // We keep all but the smallest of the largest-first inputs and
+                // replace the last one with the smallest output which is still
+                // sufficient to cover the rest of the amount
+                let (head, rest) = largest.split_at(largest.len() - 1);
+                let covered = head
+                    .iter()
+                    .fold(0u64, |sum, (_, value)| sum.saturating_add(*value));
+                let last = candidates
+                    .iter()
+                    .filter(|candidate| !head.contains(*candidate))
+                    .filter(|(_, value)| target.is_covered_by(covered.saturating_add(*value)))
+                    .last()
+                    .unwrap_or(&rest[0]);
+                let mut selected = head.to_vec();
+                selected.push(*last);
+                selected
+            }
+            SelectionStrategy::Privacy => {
+                use lnpbp::bitcoin::secp256k1::rand::{self, seq::SliceRandom};
+                candidates.shuffle(&mut rand::thread_rng());
+                let mut selected = accumulate(&candidates, target);
+                // Dropping the smallest inputs which are not required
+                selected.sort_by(|(_, value1), (_, value2)| value1.cmp(value2));
+                let mut total = selected
+                    .iter()
+                    .fold(0u64, |sum, (_, value)| sum.saturating_add(*value));
+                selected.retain(|(_, value)| {
+                    if target.is_covered_by(total - value) {
+                        total -= value;
+                        false
+                    } else {
+                        true
+                    }
+                });
+                selected
+            }
+        };
+
+        // Candidates may add up to the amount while leaving a change below
+        // the dust limit, which can't be allocated
+        let total = selected
+            .iter()
+            .fold(0u64, |sum, (_, value)| sum.saturating_add(*value));
+        if !target.is_covered_by(total) {
+            Err(SelectionError::InsufficientFunds {
+                required: amount.saturating_add(target.dust_limit),
+                available,
+            })?
+        }
+
+        Ok(selected.into_iter().map(|(outpoint, _)| outpoint).collect())
+    }
+}
+
+/// Amount which has to be covered by the selected inputs
+#[derive(Clone, Copy, Debug)]
+struct Target {
+    amount: Amount,
+    dust_limit: Amount,
+}
+
+impl Target {
+    /// Inputs cover the amount if they either match it exactly or leave a
+    /// change which is not below the dust limit
+    fn is_covered_by(&self, total: Amount) -> bool {
+        total == self.amount || total >= self.amount.saturating_add(self.dust_limit)
+    }
+}
+
+/// Takes candidates in the provided order until their sum covers the target
+fn accumulate(candidates: &[(OutPoint, Amount)], target: Target) -> Vec<(OutPoint, Amount)> {
+    let mut total = 0u64;
+    candidates
+        .iter()
+        .take_while(|(_, value)| {
+            let required = !target.is_covered_by(total);
+            total = total.saturating_add(*value);
+            required
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDateTime;
+    use lnpbp::bitcoin::hashes::Hash;
+    use lnpbp::bitcoin::{self, Txid};
+    use lnpbp::bp;
+    use lnpbp::bp::blind::OutpointReveal;
+    use lnpbp::rgb::prelude::*;
+
+    use crate::fungible::{Allocation, Coins, Supply};
+
+    fn outpoint(no: u8) -> OutPoint {
+        OutPoint {
+            txid: Txid::from_inner([no; 32]),
+            vout: no as u32,
+        }
+    }
+
+    fn revealed_amount(amount: Amount) -> amount::Revealed {
+        let seal = seal::Revealed::TxOutpoint(OutpointReveal::from(outpoint(0xFF)));
+        match AssignmentsVariant::zero_balanced(vec![], vec![(seal, amount)], vec![]) {
+            AssignmentsVariant::DiscreteFiniteField(set) => set
+                .into_iter()
+                .next()
+                .and_then(|assignment| assignment.assigned_state().cloned())
+                .expect("Zero-balanced assignment must have revealed amount"),
+            _ => unreachable!(),
+        }
+    }
+
+    // Creates asset with allocations of the given values assigned to the
+    // outpoints numbered from 1
+    fn asset(values: &[Amount], dust_limit: Amount) -> Asset {
+        let allocations = values
+            .iter()
+            .enumerate()
+            .map(|(no, value)| {
+                (
+                    outpoint(no as u8 + 1),
+                    vec![Allocation {
+                        node_id: NodeId::from_inner([no as u8 + 1; 32]),
+                        index: 0,
+                        amount: revealed_amount(*value),
+                    }],
+                )
+            })
+            .collect();
+        Asset::with(
+            ContractId::from_inner([0xAA; 32]),
+            "TST".to_string(),
+            "Test asset".to_string(),
+            None,
+            Supply::default(),
+            Coins::with_sats_precision(dust_limit, 0),
+            bp::Network::from(bitcoin::Network::Testnet),
+            0,
+            NaiveDateTime::from_timestamp(1_593_561_600, 0),
+            None,
+            vec![],
+            allocations,
+            bmap! {},
+            vec![],
+        )
+    }
+
+    fn select(
+        strategy: SelectionStrategy,
+        asset: &Asset,
+        amount: Amount,
+    ) -> Result<Vec<OutPoint>, SelectionError> {
+        strategy.select(asset, amount).map(|mut selected| {
+            selected.sort();
+            selected
+        })
+    }
+
+    #[test]
+    fn zero_amount() {
+        let asset = asset(&[50, 30], 0);
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 0),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn largest_first() {
+        let asset = asset(&[50, 30, 20, 10, 5], 0);
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 60),
+            Ok(vec![outpoint(1), outpoint(2)])
+        );
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 50),
+            Ok(vec![outpoint(1)])
+        );
+    }
+
+    #[test]
+    fn minimize_inputs() {
+        let asset = asset(&[50, 30, 20, 10, 5], 0);
+        // The last of the largest-first inputs is replaced with the smallest
+        // output which still covers the amount
+        assert_eq!(
+            select(SelectionStrategy::MinimizeInputs, &asset, 60),
+            Ok(vec![outpoint(1), outpoint(4)])
+        );
+        assert_eq!(
+            select(SelectionStrategy::MinimizeInputs, &asset, 25),
+            Ok(vec![outpoint(2)])
+        );
+        assert_eq!(
+            select(SelectionStrategy::MinimizeInputs, &asset, 110),
+            Ok(vec![outpoint(1), outpoint(2), outpoint(3), outpoint(4)])
+        );
+    }
+
+    #[test]
+    fn minimize_inputs_dust() {
+        let asset = asset(&[50, 30, 20, 10, 5], 14);
+        // Output 4 matches the amount exactly, while output 3 would leave
+        // change below the dust limit
+        assert_eq!(
+            select(SelectionStrategy::MinimizeInputs, &asset, 60),
+            Ok(vec![outpoint(1), outpoint(4)])
+        );
+        // Output 4 would leave change below the dust limit, so output 3 is
+        // the smallest one which can be used
+        assert_eq!(
+            select(SelectionStrategy::MinimizeInputs, &asset, 56),
+            Ok(vec![outpoint(1), outpoint(3)])
+        );
+    }
+
+    #[test]
+    fn privacy() {
+        // No subset of the values sums up to the amount exactly, so the
+        // selection can't depend on the order in which inputs are dropped
+        let values = [50, 30, 20, 10, 5];
+        let asset = asset(&values, 3);
+        let value = |outpoint: &OutPoint| values[outpoint.vout as usize - 1];
+        let target = Target {
+            amount: 42,
+            dust_limit: 3,
+        };
+        for _ in 0..32 {
+            let selected = select(SelectionStrategy::Privacy, &asset, 42).unwrap();
+            let total = selected.iter().map(value).sum::<Amount>();
+            assert!(target.is_covered_by(total));
+            // None of the selected inputs can be dropped
+            assert!(selected
+                .iter()
+                .all(|outpoint| !target.is_covered_by(total - value(outpoint))));
+        }
+    }
+
+    #[test]
+    fn exact_amount() {
+        let asset = asset(&[50, 30, 20], 100);
+        for strategy in &[
+            SelectionStrategy::LargestFirst,
+            SelectionStrategy::MinimizeInputs,
+            SelectionStrategy::Privacy,
+        ] {
+            assert_eq!(
+                select(*strategy, &asset, 100),
+                Ok(vec![outpoint(1), outpoint(2), outpoint(3)])
+            );
+        }
+    }
+
+    #[test]
+    fn change_below_dust() {
+        let asset = asset(&[50], 5);
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 48),
+            Err(SelectionError::InsufficientFunds {
+                required: 53,
+                available: 50
+            })
+        );
+
+        // Adding small output allows to leave change above the dust limit
+        let asset = self::asset(&[50, 3], 5);
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 48),
+            Ok(vec![outpoint(1), outpoint(2)])
+        );
+    }
+
+    #[test]
+    fn insufficient_funds() {
+        let asset = asset(&[50, 30, 20], 0);
+        for strategy in &[
+            SelectionStrategy::LargestFirst,
+            SelectionStrategy::MinimizeInputs,
+            SelectionStrategy::Privacy,
+        ] {
+            assert_eq!(
+                select(*strategy, &asset, 101),
+                Err(SelectionError::InsufficientFunds {
+                    required: 101,
+                    available: 100
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn spent_allocations_skipped() {
+        let mut asset = asset(&[50, 30, 20], 0);
+        asset.record_outgoing(
+            NodeId::from_inner([0xBB; 32]),
+            Txid::from_inner([0xBB; 32]),
+            &[outpoint(1)],
+            vec![],
+        );
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 40),
+            Ok(vec![outpoint(2), outpoint(3)])
+        );
+        assert_eq!(
+            select(SelectionStrategy::LargestFirst, &asset, 60),
+            Err(SelectionError::InsufficientFunds {
+                required: 60,
+                available: 50
+            })
+        );
+    }
+}
@@ -10,3 +10,10 @@
 // You should have received a copy of the MIT License
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
+
+//! Relational database layout shared by SQL-based caches. The tables are
+//! created by the migrations embedded from `migrations/` directory and may be
+//! queried directly by the client services.
+
+pub mod models;
+pub mod schema;
@@ -0,0 +1,90 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use chrono::NaiveDateTime;
+
+use super::schema::{allocations, assets, issues, ledger, prune_rights};
+
+/// Asset information; amounts are kept as atomic (non-fractional) values in
+/// decimal string form, since SQL integer types can't hold the full `u64` range
+#[derive(Clone, PartialEq, Eq, Debug, Queryable, Insertable, AsChangeset)]
+#[table_name = "assets"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct AssetRow {
+    pub id: String,
+    pub ticker: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub network: String,
+    pub fractional_bits: i16,
+    pub date: NaiveDateTime,
+    pub dust_limit: String,
+    pub known_circulating: String,
+    pub total_supply: Option<String>,
+    pub unspent_issue_txo: Option<String>,
+}
+
+/// Single issue from a series of issues (primary and secondary) of an asset
+#[derive(Clone, PartialEq, Eq, Debug, Queryable, Insertable)]
+#[table_name = "issues"]
+pub struct IssueRow {
+    pub asset_id: String,
+    pub series: i32,
+    pub position: i32,
+    pub issue_id: String,
+    pub txo: Option<String>,
+    pub supply: String,
+}
+
+/// Asset amount known to be assigned to a specific transaction output
+#[derive(Clone, PartialEq, Eq, Debug, Queryable, Insertable)]
+#[table_name = "allocations"]
+pub struct AllocationRow {
+    pub asset_id: String,
+    pub txid: String,
+    pub vout: i32,
+    pub node_id: String,
+    pub assignment_index: i32,
+    pub amount: String,
+    /// Hex-encoded strict serialization of the revealed amount, including
+    /// its blinding factor
+    pub revealed_amount: String,
+}
+
+/// Right to prune (burn) asset allocations assigned to a transaction output
+#[derive(Clone, PartialEq, Eq, Debug, Queryable, Insertable)]
+#[table_name = "prune_rights"]
+pub struct PruneRightRow {
+    pub asset_id: String,
+    pub txid: String,
+    pub vout: i32,
+    pub node_id: String,
+    pub assignment_index: i32,
+}
+
+/// Ledger entry recording asset movement; `outpoint` and `counterparty` are
+/// kept in string form
+#[derive(Clone, PartialEq, Eq, Debug, Queryable, Insertable)]
+#[table_name = "ledger"]
+pub struct LedgerRow {
+    pub asset_id: String,
+    pub position: i32,
+    pub operation: String,
+    pub node_id: String,
+    pub txid: String,
+    pub outpoint: Option<String>,
+    pub amount: String,
+    pub counterparty: Option<String>,
+    pub timestamp: NaiveDateTime,
+    pub pending: bool,
+}
@@ -0,0 +1,83 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+table! {
+    assets (id) {
+        id -> Text,
+        ticker -> Text,
+        name -> Text,
+        description -> Nullable<Text>,
+        network -> Text,
+        fractional_bits -> SmallInt,
+        date -> Timestamp,
+        dust_limit -> Text,
+        known_circulating -> Text,
+        total_supply -> Nullable<Text>,
+        unspent_issue_txo -> Nullable<Text>,
+    }
+}
+
+table! {
+    issues (asset_id, series, position) {
+        asset_id -> Text,
+        series -> Integer,
+        position -> Integer,
+        issue_id -> Text,
+        txo -> Nullable<Text>,
+        supply -> Text,
+    }
+}
+
+table! {
+    allocations (asset_id, txid, vout, node_id, assignment_index) {
+        asset_id -> Text,
+        txid -> Text,
+        vout -> Integer,
+        node_id -> Text,
+        assignment_index -> Integer,
+        amount -> Text,
+        revealed_amount -> Text,
+    }
+}
+
+table! {
+    prune_rights (asset_id, txid, vout) {
+        asset_id -> Text,
+        txid -> Text,
+        vout -> Integer,
+        node_id -> Text,
+        assignment_index -> Integer,
+    }
+}
+
+table! {
+    ledger (asset_id, position) {
+        asset_id -> Text,
+        position -> Integer,
+        operation -> Text,
+        node_id -> Text,
+        txid -> Text,
+        outpoint -> Nullable<Text>,
+        amount -> Text,
+        counterparty -> Nullable<Text>,
+        timestamp -> Timestamp,
+        pending -> Bool,
+    }
+}
+
+joinable!(issues -> assets (asset_id));
+joinable!(allocations -> assets (asset_id));
+joinable!(prune_rights -> assets (asset_id));
+joinable!(ledger -> assets (asset_id));
+
+allow_tables_to_appear_in_same_query!(assets, issues, allocations, prune_rights, ledger);
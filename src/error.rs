@@ -17,6 +17,7 @@ use std::collections::HashMap;
 use std::io;
 use tokio::task::JoinError;
 
+use lnpbp::bp;
 use lnpbp::lnp;
 
 #[derive(Debug, Display, Error, From)]
@@ -147,10 +148,34 @@ pub enum ServiceErrorDomain {
     Lightning,
     Schema(String),
     Anchor(String),
+    /// Genesis, consignment or other data belong to a different bitcoin
+    /// network than the one the daemon is configured for
+    WrongNetwork {
+        expected: bp::Network,
+        found: bp::Network,
+    },
+    /// Known unspent asset allocations are not sufficient to cover the
+    /// required amount (including change above the dust limit)
+    InsufficientFunds {
+        required: u64,
+        available: u64,
+    },
     #[derive_from]
     Internal(String),
 }
 
+impl ServiceErrorDomain {
+    pub fn check_network(expected: &bp::Network, found: &bp::Network) -> Result<(), Self> {
+        if expected != found {
+            Err(ServiceErrorDomain::WrongNetwork {
+                expected: expected.clone(),
+                found: found.clone(),
+            })?
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug, Display)]
 #[display_from(Debug)]
 pub enum ServiceErrorSource {
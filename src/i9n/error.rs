@@ -32,5 +32,20 @@ pub enum Error {
     #[derive_from]
     Encoding(lnpbp::strict_encoding::Error),
 
+    #[derive_from]
+    Yaml(serde_yaml::Error),
+
+    #[derive_from]
+    Json(serde_json::Error),
+
+    #[derive_from(toml::de::Error)]
+    Toml,
+
+    #[derive_from]
+    Invoice(crate::fungible::InvoiceError),
+
+    /// Consignment was rejected since it is not valid
+    InvalidConsignment(reply::ValidationStatus),
+
     UnexpectedResponse,
 }
@@ -16,20 +16,29 @@ use std::fs::File;
 use std::path::PathBuf;
 
 use lnpbp::bitcoin::consensus::encode::{deserialize, Encodable};
+use lnpbp::bitcoin::secp256k1;
 use lnpbp::bitcoin::util::psbt::{raw::Key, PartiallySignedTransaction};
-use lnpbp::bitcoin::OutPoint;
+use lnpbp::bitcoin::{OutPoint, Transaction, TxOut};
 
 use lnpbp::bp;
+use lnpbp::data_format::DataFormat;
 use lnpbp::lnp::presentation::Encode;
 use lnpbp::lnp::Unmarshall;
-use lnpbp::rgb::{Amount, PSBT_FEE_KEY, PSBT_PUBKEY_KEY};
+use lnpbp::rgb::{Amount, Consignment, ContractId, Genesis, PSBT_FEE_KEY, PSBT_PUBKEY_KEY};
+use lnpbp::strict_encoding::strict_decode;
 
 use super::{Error, Runtime};
-use crate::api::{fungible::Issue, fungible::Request, fungible::TransferApi, reply, Reply};
+use crate::api::{
+    fungible::AcceptApi, fungible::Issue, fungible::PruneApi, fungible::Request,
+    fungible::TransferApi, reply, Reply,
+};
+use crate::constants::RGB20_ADDRESS_OUTPUT_SATS;
 use crate::error::ServiceErrorDomain;
-use crate::fungible::{Invoice, IssueStructure, Outcoincealed, Outcoins, Outpoint};
+use crate::fungible::{
+    Asset, Invoice, IssueStructure, Outcoincealed, Outcoins, Outpoint, SelectionStrategy,
+};
 use crate::util::file::ReadWrite;
-use crate::util::SealSpec;
+use crate::util::{RevealSpec, SealSpec};
 
 impl Runtime {
     fn command(&mut self, command: Request) -> Result<Arc<Reply>, ServiceErrorDomain> {
@@ -42,17 +51,17 @@ impl Runtime {
 
     pub fn issue(
         &mut self,
-        _network: bp::Network,
+        network: bp::Network,
         ticker: String,
         title: String,
         description: Option<String>,
         issue_structure: IssueStructure,
         allocate: Vec<Outcoins>,
         precision: u8,
-        _prune_seals: Vec<SealSpec>,
+        prune_seals: Vec<SealSpec>,
         dust_limit: Option<Amount>,
-    ) -> Result<(), Error> {
-        // TODO: Make sure we use the same network
+    ) -> Result<(Asset, Genesis), Error> {
+        ServiceErrorDomain::check_network(&self.config.network, &network)?;
         let (supply, inflatable) = match issue_structure {
             IssueStructure::SingleIssue => (None, None),
             IssueStructure::MultipleIssues {
@@ -68,10 +77,21 @@ impl Runtime {
             inflatable,
             precision,
             dust_limit,
+            prune: prune_seals,
             allocate,
         });
         match &*self.command(command)? {
-            Reply::Success => Ok(()),
+            Reply::Issue(reply::Issue { asset, genesis }) => Ok((asset.clone(), genesis.clone())),
+            Reply::Failure(failmsg) => Err(Error::Reply(failmsg.clone())),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Blinds locally-controlled outpoint (for invoices or transfer change
+    /// outputs) with a random blinding factor, which is kept by the node
+    pub fn blind_outpoint(&mut self, outpoint: OutPoint) -> Result<bp::blind::OutpointHash, Error> {
+        match &*self.command(Request::Blind(outpoint))? {
+            Reply::BlindedOutpoint(outpoint_hash) => Ok(*outpoint_hash),
             Reply::Failure(failmsg) => Err(Error::Reply(failmsg.clone())),
             _ => Err(Error::UnexpectedResponse),
         }
@@ -80,51 +100,62 @@ impl Runtime {
     pub fn transfer(
         &mut self,
         inputs: Vec<OutPoint>,
+        selection: SelectionStrategy,
         allocate: Vec<Outcoins>,
         invoice: Invoice,
+        receiver_pubkey: Option<secp256k1::PublicKey>,
         prototype_psbt: String,
         fee: u64,
         change: Option<bp::blind::OutpointHash>,
+        address_sats: Option<u64>,
         consignment_file: String,
         transaction_file: String,
     ) -> Result<(), Error> {
-        let seal_confidential = match invoice.outpoint {
-            Outpoint::BlindedUtxo(outpoint_hash) => outpoint_hash,
-            Outpoint::Address(_address) => unimplemented!(),
-        };
+        invoice.validate(&self.config.network, receiver_pubkey.as_ref())?;
 
-        let pubkey_key = Key {
-            type_value: 0xFC,
-            key: PSBT_PUBKEY_KEY.to_vec(),
-        };
-        let fee_key = Key {
-            type_value: 0xFC,
-            key: PSBT_FEE_KEY.to_vec(),
-        };
+        let mut psbt = decode_psbt(prototype_psbt, fee)?;
 
-        let psbt_bytes = base64::decode(&prototype_psbt)?;
-        let mut psbt: PartiallySignedTransaction = deserialize(&psbt_bytes)?;
-
-        psbt.global
-            .unknown
-            .insert(fee_key, fee.to_be_bytes().to_vec());
-        for output in &mut psbt.outputs {
-            output.unknown.insert(
-                pubkey_key.clone(),
-                output.hd_keypaths.keys().next().unwrap().to_bytes(),
-            );
-        }
-        // trace!("{:?}", psbt);
+        let (theirs, theirs_witness) = match invoice.outpoint {
+            Outpoint::BlindedUtxo(seal_confidential) => (
+                vec![Outcoincealed {
+                    coins: invoice.amount,
+                    seal_confidential,
+                }],
+                vec![],
+            ),
+            Outpoint::Address(address) => {
+                // Receiver has no UTXO yet, so we create a new output paying
+                // to the invoice address and assign the assets to it
+                let vout = psbt.global.unsigned_tx.output.len() as u16;
+                psbt.global.unsigned_tx.output.push(TxOut {
+                    value: address_sats.unwrap_or(
+                        RGB20_ADDRESS_OUTPUT_SATS
+                            .parse()
+                            .expect("Error in RGB20_ADDRESS_OUTPUT_SATS constant value"),
+                    ),
+                    script_pubkey: address.script_pubkey(),
+                });
+                psbt.outputs.push(Default::default());
+                (
+                    vec![],
+                    vec![Outcoins {
+                        coins: invoice.amount,
+                        vout,
+                        txid: None,
+                    }],
+                )
+            }
+        };
 
         let api = TransferApi {
+            network: self.config.network.clone(),
             psbt,
             contract_id: invoice.contract_id,
             inputs,
+            selection,
             ours: allocate,
-            theirs: vec![Outcoincealed {
-                coins: invoice.amount,
-                seal_confidential,
-            }],
+            theirs,
+            theirs_witness,
             change,
         };
 
@@ -150,6 +181,78 @@ impl Runtime {
         }
     }
 
+    pub fn prune(
+        &mut self,
+        contract_id: ContractId,
+        prune_right: OutPoint,
+        inputs: Vec<OutPoint>,
+        remainder: Vec<Outcoins>,
+        next_prune: Option<SealSpec>,
+        proof: Option<Vec<u8>>,
+        prototype_psbt: String,
+        fee: u64,
+        consignment_file: String,
+        transaction_file: String,
+    ) -> Result<(), Error> {
+        let api = PruneApi {
+            contract_id,
+            network: self.config.network.clone(),
+            psbt: decode_psbt(prototype_psbt, fee)?,
+            prune_right,
+            inputs,
+            remainder,
+            next_prune,
+            proof,
+        };
+
+        match &*self.command(Request::Prune(api))? {
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::Transfer(transfer) => {
+                transfer
+                    .consignment
+                    .write_file(PathBuf::from(&consignment_file))?;
+                let out_file =
+                    File::create(&transaction_file).expect("can't create output transaction file");
+                transfer.psbt.consensus_encode(out_file)?;
+                println!(
+                    "Pruning succeeded, consignment data are written to {:?}, partially signed witness transaction to {:?}",
+                    consignment_file, transaction_file
+                );
+
+                Ok(())
+            }
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
+    /// Accepts incoming transfer. Reveal data are required only for the
+    /// outpoints blinded outside of the node; all other endpoints of the
+    /// consignment are matched by the node itself
+    pub fn accept(
+        &mut self,
+        consignment_file: String,
+        reveal_outpoints: Vec<RevealSpec>,
+        invoices: Vec<Invoice>,
+        witness_txs: Vec<Transaction>,
+    ) -> Result<(), Error> {
+        let api = AcceptApi {
+            consignment: Consignment::read_file(PathBuf::from(&consignment_file))?,
+            reveal_outpoints: reveal_outpoints
+                .into_iter()
+                .map(bp::blind::OutpointReveal::from)
+                .collect(),
+            invoices,
+            witness_txs,
+        };
+
+        match &*self.command(Request::Accept(api))? {
+            Reply::Success => Ok(()),
+            Reply::Failure(failure) => Err(Error::Reply(failure.clone())),
+            Reply::ValidationStatus(status) => Err(Error::InvalidConsignment(status.clone())),
+            _ => Err(Error::UnexpectedResponse),
+        }
+    }
+
     pub fn sync(&mut self) -> Result<reply::SyncFormat, Error> {
         match &*self.command(Request::Sync)? {
             Reply::Sync(data) => Ok(data.clone()),
@@ -157,4 +260,45 @@ impl Runtime {
             _ => Err(Error::UnexpectedResponse),
         }
     }
+
+    /// Returns list of all known assets, decoded from the `Sync` reply data
+    /// regardless of the data format used by the fungible daemon
+    pub fn assets(&mut self) -> Result<Vec<Asset>, Error> {
+        let reply::SyncFormat(format, data) = self.sync()?;
+        Ok(match format {
+            DataFormat::Yaml => serde_yaml::from_slice(&data)?,
+            DataFormat::Json => serde_json::from_slice(&data)?,
+            DataFormat::Toml => toml::from_slice(&data)?,
+            DataFormat::StrictEncode => strict_decode(&data)?,
+        })
+    }
+}
+
+/// Decodes base64-encoded partially-signed transaction prototype and adds the
+/// fee and output public key information required for RGB commitments
+fn decode_psbt(prototype_psbt: String, fee: u64) -> Result<PartiallySignedTransaction, Error> {
+    let pubkey_key = Key {
+        type_value: 0xFC,
+        key: PSBT_PUBKEY_KEY.to_vec(),
+    };
+    let fee_key = Key {
+        type_value: 0xFC,
+        key: PSBT_FEE_KEY.to_vec(),
+    };
+
+    let psbt_bytes = base64::decode(&prototype_psbt)?;
+    let mut psbt: PartiallySignedTransaction = deserialize(&psbt_bytes)?;
+
+    psbt.global
+        .unknown
+        .insert(fee_key, fee.to_be_bytes().to_vec());
+    for output in &mut psbt.outputs {
+        output.unknown.insert(
+            pubkey_key.clone(),
+            output.hd_keypaths.keys().next().unwrap().to_bytes(),
+        );
+    }
+    // trace!("{:?}", psbt);
+
+    Ok(psbt)
 }
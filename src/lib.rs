@@ -34,6 +34,10 @@ extern crate derive_wrapper;
 extern crate async_trait;
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 
 #[macro_use]
 pub extern crate lnpbp;
@@ -44,6 +48,7 @@ pub mod api;
 pub mod cli;
 pub mod constants;
 mod contracts;
+pub mod db;
 pub mod error;
 pub mod i9n;
 pub mod rgbd;
@@ -20,6 +20,7 @@ use lnpbp::bp;
 use lnpbp::lnp::transport::zmq::SocketLocator;
 use lnpbp::lnp::{LocalNode, NodeLocator};
 
+use super::TxResolverConfig;
 use crate::constants::*;
 
 #[derive(Clap)]
@@ -69,6 +70,19 @@ pub struct Opts {
     /// Bitcoin network to use
     #[clap(short, long, default_value = RGB_NETWORK, env = "RGB_NETWORK")]
     pub network: bp::Network,
+
+    /// Bitcoin transaction resolver used for consignment validation, in form
+    /// of `electrum://<host>:<port>`, `bitcoind://<user>:<pass>@<host>:<port>`
+    /// or `file://<path>` for a file with hex-encoded raw transactions
+    #[clap(short, long, env = "RGB_STASHD_RESOLVER")]
+    pub resolver: Option<String>,
+
+    /// Merges consignments with witness transactions which can't be resolved
+    /// (for instance, when no transaction resolver is configured). Anchors of
+    /// such consignments are not checked against the blockchain, so this must
+    /// be used for testing purposes only
+    #[clap(long)]
+    pub allow_unresolved: bool,
 }
 
 // We need config structure since not all of the parameters can be specified
@@ -86,6 +100,8 @@ pub struct Config {
     pub rpc_endpoint: SocketLocator,
     pub pub_endpoint: SocketLocator,
     pub network: bp::Network,
+    pub resolver: Option<TxResolverConfig>,
+    pub allow_unresolved: bool,
 }
 
 impl From<Opts> for Config {
@@ -93,6 +109,7 @@ impl From<Opts> for Config {
         let mut me = Self {
             verbose: opts.verbose,
             network: opts.network,
+            allow_unresolved: opts.allow_unresolved,
             ..Config::default()
         };
         me.data_dir = me.parse_param(opts.data_dir);
@@ -101,6 +118,7 @@ impl From<Opts> for Config {
         me.rpc_endpoint = me.parse_param(opts.rpc_endpoint);
         me.pub_endpoint = me.parse_param(opts.pub_endpoint);
         me.p2p_endpoint = opts.p2p_endpoint.map(|ep| me.parse_param(ep));
+        me.resolver = opts.resolver.map(|resolver| me.parse_param(resolver));
         me
     }
 }
@@ -125,6 +143,8 @@ impl Default for Config {
             network: RGB_NETWORK
                 .parse()
                 .expect("Error in RGB_NETWORK constant value"),
+            resolver: None,
+            allow_unresolved: false,
         }
     }
 }
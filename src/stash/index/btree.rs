@@ -16,6 +16,7 @@ use std::fs;
 use std::io;
 use std::path::PathBuf;
 
+use lnpbp::bitcoin::hashes::{self, Hash};
 use lnpbp::rgb::{Anchor, AnchorId, NodeId};
 use lnpbp::strict_encoding::{StrictDecode, StrictEncode};
 
@@ -24,6 +25,10 @@ use crate::error::{BootstrapError, ServiceErrorDomain};
 
 type BTreeIndexData = BTreeMap<Vec<u8>, Vec<u8>>;
 
+/// Prefix for the index keys mapping transition ids to the ids of the anchors
+/// committing to them
+const INDEX_ANCHOR_BY_TRANSITION: u8 = 0x01;
+
 #[derive(Debug, Display, Error, From)]
 #[display_from(Debug)]
 pub enum BTreeIndexError {
@@ -32,6 +37,11 @@ pub enum BTreeIndexError {
 
     #[derive_from]
     Encoding(lnpbp::strict_encoding::Error),
+
+    #[derive_from(hashes::Error)]
+    BrokenHashData,
+
+    AnchorNotFound(NodeId),
 }
 
 impl From<BTreeIndexError> for ServiceErrorDomain {
@@ -90,16 +100,62 @@ impl BTreeIndex {
         self.index.strict_encode(file)?;
         Ok(())
     }
+
+    #[inline]
+    fn anchor_by_transition_key(tsid: &NodeId) -> Vec<u8> {
+        let mut key = vec![INDEX_ANCHOR_BY_TRANSITION];
+        key.extend(&tsid.into_inner());
+        key
+    }
 }
 
 impl Index for BTreeIndex {
     type Error = BTreeIndexError;
 
-    fn anchor_id_by_transition_id(&self, _tsid: NodeId) -> Result<AnchorId, Self::Error> {
-        unimplemented!()
+    fn anchor_id_by_transition_id(&self, tsid: NodeId) -> Result<AnchorId, Self::Error> {
+        let anchor_id = self
+            .index
+            .get(&Self::anchor_by_transition_key(&tsid))
+            .ok_or(BTreeIndexError::AnchorNotFound(tsid))?;
+        Ok(AnchorId::from_slice(anchor_id)?)
     }
 
-    fn index_anchor(&mut self, _anchor: &Anchor) -> Result<bool, Self::Error> {
-        unimplemented!()
+    fn index_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        let anchor_id = anchor.anchor_id().into_inner().to_vec();
+        let mut updated = false;
+        // Anchor commits to the state transitions through the multi-message
+        // commitment items, where each of the items is a transition id
+        // (the rest of the items are random entropy, and keeping them in the
+        // index does not hurt)
+        for item in &anchor.commitment.commitments {
+            let tsid = NodeId::from_inner(item.commitment.into_inner());
+            let prev = self
+                .index
+                .insert(Self::anchor_by_transition_key(&tsid), anchor_id.clone());
+            updated |= prev.as_ref() != Some(&anchor_id);
+        }
+        if updated {
+            self.store()?;
+        }
+        Ok(updated)
+    }
+
+    fn remove_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        let anchor_id = anchor.anchor_id().into_inner().to_vec();
+        let mut updated = false;
+        for item in &anchor.commitment.commitments {
+            let key =
+                Self::anchor_by_transition_key(&NodeId::from_inner(item.commitment.into_inner()));
+            // Do not touch index entries which were re-assigned to some other
+            // anchor committing to the same transition
+            if self.index.get(&key) == Some(&anchor_id) {
+                self.index.remove(&key);
+                updated = true;
+            }
+        }
+        if updated {
+            self.store()?;
+        }
+        Ok(updated)
     }
 }
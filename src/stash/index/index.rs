@@ -21,4 +21,6 @@ pub trait Index {
     fn anchor_id_by_transition_id(&self, tsid: NodeId) -> Result<AnchorId, Self::Error>;
 
     fn index_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error>;
+
+    fn remove_anchor(&mut self, anchor: &Anchor) -> Result<bool, Self::Error>;
 }
@@ -12,11 +12,15 @@
 // If not, see <https://opensource.org/licenses/MIT>.
 
 mod config;
+mod reveal;
 mod runtime;
 mod stash;
 
 pub(self) mod index;
+pub(self) mod resolver;
 pub(self) mod storage;
 
 pub use config::{Config, Opts};
+pub use reveal::RevealSeals;
+pub use resolver::{TxResolverConfig, TxResolverError};
 pub use runtime::{main_with_config, Runtime};
@@ -0,0 +1,107 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::fmt;
+use serde_json::json;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use lnpbp::bitcoin::consensus::deserialize;
+use lnpbp::bitcoin::hashes::hex::FromHex;
+use lnpbp::bitcoin::{Transaction, Txid};
+
+use super::{TxResolve, TxResolverError};
+
+/// Transaction resolver using Bitcoin Core JSON-RPC `getrawtransaction` call.
+/// Bitcoin Core node must be running with `txindex=1`
+#[derive(Clone, PartialEq, Eq, Display)]
+#[display_from(Debug)]
+pub struct BitcoindResolver {
+    server: String,
+    auth: String,
+}
+
+// Authorization data contain RPC password and must not get into the logs
+impl fmt::Debug for BitcoindResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitcoindResolver")
+            .field("server", &self.server)
+            .field("auth", &"***")
+            .finish()
+    }
+}
+
+impl BitcoindResolver {
+    pub fn new(server: &str, user: &str, password: &str) -> Self {
+        debug!(
+            "Instantiating Bitcoin Core transaction resolver for {} ...",
+            server
+        );
+        Self {
+            server: server.to_string(),
+            auth: base64::encode(format!("{}:{}", user, password)),
+        }
+    }
+}
+
+impl TxResolve for BitcoindResolver {
+    fn raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, TxResolverError> {
+        trace!("Requesting transaction {} from Bitcoin Core", txid);
+        let body = json!({
+            "jsonrpc": "1.0",
+            "id": "rgb",
+            "method": "getrawtransaction",
+            "params": [txid.to_string()]
+        })
+        .to_string();
+
+        // We use HTTP/1.0 to get non-chunked response closed by the server
+        let mut stream = TcpStream::connect(&self.server)?;
+        write!(
+            stream,
+            "POST / HTTP/1.0\r\n\
+             Host: {}\r\n\
+             Authorization: Basic {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             \r\n\
+             {}",
+            self.server,
+            self.auth,
+            body.len(),
+            body
+        )?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        let payload = response
+            .splitn(2, "\r\n\r\n")
+            .nth(1)
+            .ok_or(TxResolverError::MalformedResponse(response.clone()))?;
+        let reply: serde_json::Value = serde_json::from_str(payload)?;
+
+        // Bitcoin Core returns error object (with HTTP 500 status) for unknown
+        // transactions
+        if !reply["error"].is_null() {
+            debug!(
+                "Bitcoin Core has not returned transaction {}: {}",
+                txid, reply["error"]
+            );
+            return Ok(None);
+        }
+        let hex = reply["result"]
+            .as_str()
+            .ok_or(TxResolverError::MalformedResponse(payload.to_string()))?;
+        Ok(Some(deserialize(&Vec::<u8>::from_hex(hex)?)?))
+    }
+}
@@ -0,0 +1,73 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use serde_json::json;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+use lnpbp::bitcoin::consensus::deserialize;
+use lnpbp::bitcoin::hashes::hex::FromHex;
+use lnpbp::bitcoin::{Transaction, Txid};
+
+use super::{TxResolve, TxResolverError};
+
+/// Transaction resolver querying Electrum server over its line-delimited
+/// JSON-RPC protocol (plain TCP connection)
+#[derive(Clone, PartialEq, Eq, Debug, Display)]
+#[display_from(Debug)]
+pub struct ElectrumResolver {
+    server: String,
+}
+
+impl ElectrumResolver {
+    pub fn new(server: &str) -> Self {
+        debug!(
+            "Instantiating Electrum transaction resolver for {} ...",
+            server
+        );
+        Self {
+            server: server.to_string(),
+        }
+    }
+}
+
+impl TxResolve for ElectrumResolver {
+    fn raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, TxResolverError> {
+        trace!("Requesting transaction {} from Electrum server", txid);
+        let mut stream = TcpStream::connect(&self.server)?;
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "blockchain.transaction.get",
+            "params": [txid.to_string()]
+        });
+        stream.write_all(format!("{}\n", request).as_bytes())?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        let response: serde_json::Value = serde_json::from_str(&line)?;
+
+        // Electrum returns error object for unknown transactions
+        if !response["error"].is_null() {
+            debug!(
+                "Electrum server has not returned transaction {}: {}",
+                txid, response["error"]
+            );
+            return Ok(None);
+        }
+        let hex = response["result"]
+            .as_str()
+            .ok_or(TxResolverError::MalformedResponse(line.clone()))?;
+        Ok(Some(deserialize(&Vec::<u8>::from_hex(hex)?)?))
+    }
+}
@@ -0,0 +1,72 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use lnpbp::bitcoin::consensus::deserialize;
+use lnpbp::bitcoin::hashes::hex::FromHex;
+use lnpbp::bitcoin::{Transaction, Txid};
+
+use super::{TxResolve, TxResolverError};
+
+/// Transaction resolver for offline and regtest use. Reads transactions from
+/// a file containing hex-encoded raw transactions, one per line. Each line
+/// may be followed by a transaction fee (in satoshis), separated by a
+/// whitespace; otherwise the fee is computed from the transactions spent by
+/// the resolved one, which has to be present in the same file.
+#[derive(Clone, PartialEq, Debug, Display)]
+#[display_from(Debug)]
+pub struct FileResolver {
+    transactions: HashMap<Txid, (Transaction, Option<u64>)>,
+}
+
+impl FileResolver {
+    pub fn load(path: &PathBuf) -> Result<Self, TxResolverError> {
+        debug!("Loading transactions for the resolver from {:?} ...", path);
+        let mut transactions = HashMap::new();
+        for line in fs::read_to_string(path)?.lines() {
+            let mut parts = line.split_whitespace();
+            let hex = match parts.next() {
+                Some(hex) if !hex.starts_with('#') => hex,
+                _ => continue,
+            };
+            let tx: Transaction = deserialize(&Vec::<u8>::from_hex(hex)?)?;
+            let fee = parts
+                .next()
+                .map(|fee| {
+                    fee.parse()
+                        .map_err(|_| TxResolverError::WrongConfig(format!("Wrong fee: {}", fee)))
+                })
+                .transpose()?;
+            transactions.insert(tx.txid(), (tx, fee));
+        }
+        debug!("{} transactions are loaded", transactions.len());
+        Ok(Self { transactions })
+    }
+}
+
+impl TxResolve for FileResolver {
+    fn raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, TxResolverError> {
+        Ok(self.transactions.get(txid).map(|(tx, _)| tx.clone()))
+    }
+
+    fn resolve(&self, txid: &Txid) -> Result<Option<(Transaction, u64)>, TxResolverError> {
+        match self.transactions.get(txid) {
+            Some((tx, Some(fee))) => Ok(Some((tx.clone(), *fee))),
+            Some((tx, None)) => Ok(Some((tx.clone(), self.fee(tx)?))),
+            None => Ok(None),
+        }
+    }
+}
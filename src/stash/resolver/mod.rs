@@ -10,3 +10,16 @@
 // You should have received a copy of the MIT License
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
+
+mod resolver;
+
+mod bitcoind;
+mod electrum;
+mod file;
+
+pub(super) use resolver::TxResolve;
+pub use resolver::{TxResolverConfig, TxResolverError};
+
+pub(super) use bitcoind::BitcoindResolver;
+pub(super) use electrum::ElectrumResolver;
+pub(super) use file::FileResolver;
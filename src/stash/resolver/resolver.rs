@@ -0,0 +1,199 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+use std::io;
+use std::path::PathBuf;
+use url::Url;
+
+use lnpbp::bitcoin::{self, OutPoint, Transaction, Txid};
+
+use super::{BitcoindResolver, ElectrumResolver, FileResolver};
+use crate::error::{BootstrapError, ServiceErrorDomain};
+
+#[derive(Debug, Display, Error, From)]
+#[display_from(Debug)]
+pub enum TxResolverError {
+    #[derive_from]
+    Io(io::Error),
+
+    #[derive_from]
+    Json(serde_json::Error),
+
+    #[derive_from(bitcoin::hashes::hex::Error)]
+    HexEncoding,
+
+    #[derive_from]
+    ConsensusEncoding(bitcoin::consensus::encode::Error),
+
+    /// Server has returned response which can't be interpreted
+    MalformedResponse(String),
+
+    /// Unable to find transaction spent by the resolved transaction, so the
+    /// fee can't be computed
+    UnknownPrevout(OutPoint),
+
+    /// Transaction spends less than it pays
+    NegativeFee(Txid),
+
+    WrongConfig(String),
+}
+
+impl From<TxResolverError> for ServiceErrorDomain {
+    fn from(_: TxResolverError) -> Self {
+        ServiceErrorDomain::Bitcoin
+    }
+}
+
+impl From<TxResolverError> for BootstrapError {
+    fn from(err: TxResolverError) -> Self {
+        BootstrapError::ArgParseError(format!("{}", err))
+    }
+}
+
+/// Bitcoin transaction provider used during consignment validation.
+///
+/// Implementors have to provide only [TxResolve::raw_tx] method; transaction
+/// fee is computed by the default implementation of [TxResolve::fee] using
+/// transactions spent by the resolved one.
+pub trait TxResolve: Send {
+    fn raw_tx(&self, txid: &Txid) -> Result<Option<Transaction>, TxResolverError>;
+
+    fn resolve(&self, txid: &Txid) -> Result<Option<(Transaction, u64)>, TxResolverError> {
+        match self.raw_tx(txid)? {
+            Some(tx) => {
+                let fee = self.fee(&tx)?;
+                Ok(Some((tx, fee)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn fee(&self, tx: &Transaction) -> Result<u64, TxResolverError> {
+        if tx.is_coin_base() {
+            return Ok(0);
+        }
+
+        let mut inputs = 0u64;
+        for txin in &tx.input {
+            let prevout = txin.previous_output;
+            inputs += self
+                .raw_tx(&prevout.txid)?
+                .and_then(|prev_tx| prev_tx.output.get(prevout.vout as usize).cloned())
+                .ok_or(TxResolverError::UnknownPrevout(prevout))?
+                .value;
+        }
+        let outputs = tx.output.iter().fold(0u64, |sum, txout| sum + txout.value);
+        inputs
+            .checked_sub(outputs)
+            .ok_or(TxResolverError::NegativeFee(tx.txid()))
+    }
+}
+
+/// Configuration of the transaction resolver, parsed from a connection string:
+/// - `electrum://<host>:<port>` for Electrum server;
+/// - `bitcoind://<user>:<password>@<host>:<port>` for Bitcoin Core JSON-RPC
+///   (requires `txindex=1`);
+/// - `file://<path>` for a file containing hex-encoded raw transactions, one
+///   per line, optionally followed by the transaction fee
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum TxResolverConfig {
+    Electrum {
+        server: String,
+    },
+    Bitcoind {
+        server: String,
+        user: String,
+        password: String,
+    },
+    File {
+        path: PathBuf,
+    },
+}
+
+impl TxResolverConfig {
+    pub fn resolver(&self) -> Result<Box<dyn TxResolve>, TxResolverError> {
+        Ok(match self {
+            TxResolverConfig::Electrum { server } => Box::new(ElectrumResolver::new(server)),
+            TxResolverConfig::Bitcoind {
+                server,
+                user,
+                password,
+            } => Box::new(BitcoindResolver::new(server, user, password)),
+            TxResolverConfig::File { path } => Box::new(FileResolver::load(path)?),
+        })
+    }
+}
+
+impl FromStr for TxResolverConfig {
+    type Err = TxResolverError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(s).map_err(|err| TxResolverError::WrongConfig(format!("{}", err)))?;
+        let server = || -> Result<String, TxResolverError> {
+            match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) => Ok(format!("{}:{}", host, port)),
+                _ => Err(TxResolverError::WrongConfig(format!(
+                    "Transaction resolver address must contain host and port: {}",
+                    s
+                ))),
+            }
+        };
+        Ok(match url.scheme() {
+            "electrum" => TxResolverConfig::Electrum { server: server()? },
+            "bitcoind" => TxResolverConfig::Bitcoind {
+                server: server()?,
+                user: url.username().to_string(),
+                password: url.password().unwrap_or_default().to_string(),
+            },
+            "file" => TxResolverConfig::File {
+                path: PathBuf::from(url.path()),
+            },
+            scheme => Err(TxResolverError::WrongConfig(format!(
+                "Unknown transaction resolver type: {}",
+                scheme
+            )))?,
+        })
+    }
+}
+
+impl Display for TxResolverConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TxResolverConfig::Electrum { server } => write!(f, "electrum://{}", server),
+            TxResolverConfig::Bitcoind { server, user, .. } => {
+                write!(f, "bitcoind://{}:***@{}", user, server)
+            }
+            TxResolverConfig::File { path } => write!(f, "file://{}", path.display()),
+        }
+    }
+}
+
+// RPC password is redacted, so the configuration can be logged safely
+impl fmt::Debug for TxResolverConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TxResolverConfig::Electrum { server } => {
+                f.debug_struct("Electrum").field("server", server).finish()
+            }
+            TxResolverConfig::Bitcoind { server, user, .. } => f
+                .debug_struct("Bitcoind")
+                .field("server", server)
+                .field("user", user)
+                .field("password", &"***")
+                .finish(),
+            TxResolverConfig::File { path } => f.debug_struct("File").field("path", path).finish(),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::collections::HashMap;
+
+use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
+use lnpbp::client_side_validation::Conceal;
+use lnpbp::rgb::{seal, Assignment, AssignmentsVariant, Node};
+
+/// Reveals seals that are known to the local party (i.e. were blinded by us
+/// when creating an invoice) inside node assignments of all types, including
+/// declarative ones (like issuance and prune rights).
+///
+/// [PRIVACY]: the sender of the consignment does not know the actual seal
+/// data, so we have to update the node with the revealed seal information
+/// that we kept since the invoice was created.
+pub trait RevealSeals {
+    /// Returns number of assignments which seals were revealed
+    fn reveal_seals(&mut self, known_seals: &[OutpointReveal]) -> usize;
+}
+
+impl<T> RevealSeals for T
+where
+    T: Node,
+{
+    fn reveal_seals(&mut self, known_seals: &[OutpointReveal]) -> usize {
+        let known_seals: HashMap<OutpointHash, OutpointReveal> = known_seals
+            .iter()
+            .map(|reveal| (reveal.conceal(), reveal.clone()))
+            .collect();
+
+        self.assignments_mut()
+            .into_iter()
+            .map(|(_, variant)| reveal_variant(variant, &known_seals))
+            .sum()
+    }
+}
+
+fn reveal_variant(
+    variant: &mut AssignmentsVariant,
+    known_seals: &HashMap<OutpointHash, OutpointReveal>,
+) -> usize {
+    // Assignment sets of different variants have different state types, so
+    // we use macro instead of a generic function
+    macro_rules! reveal_set {
+        ($set:ident) => {{
+            let mut count = 0usize;
+            for assignment in $set.clone() {
+                let revealed = match &assignment {
+                    Assignment::Confidential {
+                        seal_definition,
+                        assigned_state,
+                    } => known_seals.get(seal_definition).map(|reveal| {
+                        Assignment::ConfidentialAmount {
+                            seal_definition: seal::Revealed::TxOutpoint(reveal.clone()),
+                            assigned_state: assigned_state.clone(),
+                        }
+                    }),
+                    Assignment::ConfidentialSeal {
+                        seal_definition,
+                        assigned_state,
+                    } => known_seals
+                        .get(seal_definition)
+                        .map(|reveal| Assignment::Revealed {
+                            seal_definition: seal::Revealed::TxOutpoint(reveal.clone()),
+                            assigned_state: assigned_state.clone(),
+                        }),
+                    _ => None,
+                };
+                if let Some(revealed) = revealed {
+                    $set.remove(&assignment);
+                    $set.insert(revealed);
+                    count += 1;
+                }
+            }
+            count
+        }};
+    }
+
+    match variant {
+        AssignmentsVariant::Declarative(set) => reveal_set!(set),
+        AssignmentsVariant::DiscreteFiniteField(set) => reveal_set!(set),
+        AssignmentsVariant::CustomData(set) => reveal_set!(set),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use lnpbp::bitcoin::hashes::Hash;
+    use lnpbp::bitcoin::Txid;
+    use lnpbp::rgb::{data, Amount};
+
+    fn outpoint_reveal(no: u8) -> OutpointReveal {
+        OutpointReveal {
+            blinding: no as u64 * 1000,
+            txid: Txid::from_inner([no; 32]),
+            vout: no as u16,
+        }
+    }
+
+    fn amount_revealed(amount: Amount) -> ::lnpbp::rgb::amount::Revealed {
+        let seal = seal::Revealed::TxOutpoint(outpoint_reveal(0xFF));
+        match AssignmentsVariant::zero_balanced(vec![], vec![(seal, amount)], vec![]) {
+            AssignmentsVariant::DiscreteFiniteField(set) => set
+                .into_iter()
+                .next()
+                .and_then(|assignment| assignment.assigned_state().cloned())
+                .expect("Zero-balanced assignment must have revealed amount"),
+            _ => unreachable!(),
+        }
+    }
+
+    // Creates assignment set with all four assignment forms, where the
+    // confidential seals are defined by outpoints 1 (with confidential state)
+    // and 2 (with revealed state) and the revealed ones by outpoints 3 and 4.
+    // Confidential seal defined by outpoint 5 is not known to the test.
+    macro_rules! assignment_set {
+        ($state:expr) => {{
+            let state = $state;
+            bset![
+                Assignment::Confidential {
+                    seal_definition: outpoint_reveal(1).conceal(),
+                    assigned_state: state.conceal(),
+                },
+                Assignment::ConfidentialSeal {
+                    seal_definition: outpoint_reveal(2).conceal(),
+                    assigned_state: state.clone(),
+                },
+                Assignment::ConfidentialAmount {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal(3)),
+                    assigned_state: state.conceal(),
+                },
+                Assignment::Revealed {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal(4)),
+                    assigned_state: state.clone(),
+                },
+                Assignment::Confidential {
+                    seal_definition: outpoint_reveal(5).conceal(),
+                    assigned_state: state.conceal(),
+                }
+            ]
+        }};
+    }
+
+    // Set expected after revealing seals for outpoints 1 to 4
+    macro_rules! revealed_set {
+        ($state:expr) => {{
+            let state = $state;
+            bset![
+                Assignment::ConfidentialAmount {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal(1)),
+                    assigned_state: state.conceal(),
+                },
+                Assignment::Revealed {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal(2)),
+                    assigned_state: state.clone(),
+                },
+                Assignment::ConfidentialAmount {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal(3)),
+                    assigned_state: state.conceal(),
+                },
+                Assignment::Revealed {
+                    seal_definition: seal::Revealed::TxOutpoint(outpoint_reveal(4)),
+                    assigned_state: state.clone(),
+                },
+                Assignment::Confidential {
+                    seal_definition: outpoint_reveal(5).conceal(),
+                    assigned_state: state.conceal(),
+                }
+            ]
+        }};
+    }
+
+    fn known_seals() -> HashMap<OutpointHash, OutpointReveal> {
+        (1..=4)
+            .map(outpoint_reveal)
+            .map(|reveal| (reveal.conceal(), reveal))
+            .collect()
+    }
+
+    #[test]
+    fn test_reveal_declarative() {
+        let mut variant = AssignmentsVariant::Declarative(assignment_set!(data::Void));
+        assert_eq!(reveal_variant(&mut variant, &known_seals()), 2);
+        assert_eq!(
+            variant,
+            AssignmentsVariant::Declarative(revealed_set!(data::Void))
+        );
+    }
+
+    #[test]
+    fn test_reveal_discrete_finite_field() {
+        let amount = amount_revealed(1000);
+        let mut variant = AssignmentsVariant::DiscreteFiniteField(assignment_set!(amount.clone()));
+        assert_eq!(reveal_variant(&mut variant, &known_seals()), 2);
+        assert_eq!(
+            variant,
+            AssignmentsVariant::DiscreteFiniteField(revealed_set!(amount))
+        );
+    }
+
+    #[test]
+    fn test_reveal_custom_data() {
+        let data = data::Revealed::U64(42);
+        let mut variant = AssignmentsVariant::CustomData(assignment_set!(data.clone()));
+        assert_eq!(reveal_variant(&mut variant, &known_seals()), 2);
+        assert_eq!(variant, AssignmentsVariant::CustomData(revealed_set!(data)));
+    }
+
+    #[test]
+    fn test_reveal_unknown_seals() {
+        let mut variant = AssignmentsVariant::Declarative(assignment_set!(data::Void));
+        let known = vec![outpoint_reveal(6)]
+            .into_iter()
+            .map(|reveal| (reveal.conceal(), reveal))
+            .collect();
+        assert_eq!(reveal_variant(&mut variant, &known), 0);
+        assert_eq!(
+            variant,
+            AssignmentsVariant::Declarative(assignment_set!(data::Void))
+        );
+    }
+}
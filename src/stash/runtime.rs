@@ -11,26 +11,26 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::HashMap;
 use std::path::PathBuf;
 
-use lnpbp::bitcoin::{Transaction, Txid};
-use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
-use lnpbp::client_side_validation::Conceal;
 use lnpbp::lnp::presentation::Encode;
 use lnpbp::lnp::zmq::ApiType;
 use lnpbp::lnp::{transport, NoEncryption, Session, Unmarshall, Unmarshaller};
 use lnpbp::rgb::{
-    seal, validation, Anchor, Assignment, AssignmentsVariant, Consignment, ContractId, Genesis,
-    Node, NodeId, Schema, Validity,
+    Anchor, Consignment, ContractId, Genesis, Node, NodeId, Schema, SchemaId, Validity,
 };
 use lnpbp::TryService;
 
 use super::index::{BTreeIndex, Index};
-#[cfg(not(store_hammersbald))] // Default store
-use super::storage::{DiskStorage, DiskStorageConfig, Store};
+use super::resolver::TxResolve;
+use super::reveal::RevealSeals;
+use super::storage::Store;
+#[cfg(not(feature = "store_sled"))] // Default store
+use super::storage::{DiskStorage, DiskStorageConfig};
+#[cfg(feature = "store_sled")]
+use super::storage::{SledStorage, SledStorageConfig};
 use super::Config;
-use crate::api::stash::{ConsignRequest, MergeRequest, Request};
+use crate::api::stash::{ConsignRequest, MergeRequest, Notification, Request};
 use crate::api::{reply, Reply};
 use crate::error::{
     BootstrapError, RuntimeError, ServiceError, ServiceErrorDomain, ServiceErrorSource,
@@ -58,11 +58,16 @@ pub struct Runtime {
     /// large binary blob values. Fast read, slow write, no delete db.
     /// Must be exclusive for the current service and must not be used
     /// from anywhere else. The disk storage must be locked for exclusive
-    /// access.
-    #[cfg(not(store_hammersbald))] // Default store
+    /// access. Plain files are used by default; embedded sled database may
+    /// be chosen instead with `store_sled` cargo feature.
+    #[cfg(not(feature = "store_sled"))] // Default store
     pub(super) storage: DiskStorage,
-    #[cfg(all(store_hammersbald, not(any(store_disk))))]
-    pub(super) storage: HammersbaldStore,
+    #[cfg(feature = "store_sled")]
+    pub(super) storage: SledStorage,
+
+    /// Bitcoin transaction resolver used during consignment validation;
+    /// if none is configured, all transactions are reported as unresolved
+    pub(super) resolver: Option<Box<dyn TxResolve>>,
 
     /// Unmarshaller instance used for parsing RPC request
     unmarshaller: Unmarshaller<Request>,
@@ -82,15 +87,25 @@ impl Runtime {
     }
 
     pub fn init(config: Config, mut context: &mut zmq::Context) -> Result<Self, BootstrapError> {
-        #[cfg(not(store_hammersbald))] // Default store
+        #[cfg(not(feature = "store_sled"))] // Default store
         let storage = DiskStorage::new(DiskStorageConfig {
             data_dir: PathBuf::from(config.stash.clone()),
         })?;
+        #[cfg(feature = "store_sled")]
+        let storage = SledStorage::new(SledStorageConfig {
+            data_dir: PathBuf::from(config.stash.clone()),
+        })?;
 
         let indexer = BTreeIndex::load(BTreeIndexConfig {
             index_file: PathBuf::from(config.index.clone()),
         })?;
 
+        let resolver = config
+            .resolver
+            .as_ref()
+            .map(|resolver_config| resolver_config.resolver())
+            .transpose()?;
+
         let session_rpc = Session::new_zmq_unencrypted(
             ApiType::Server,
             &mut context,
@@ -111,6 +126,7 @@ impl Runtime {
             session_pub,
             indexer,
             storage,
+            resolver,
             unmarshaller: Request::create_unmarshaller(),
         })
     }
@@ -156,14 +172,19 @@ impl Runtime {
             .map_err(|err| ServiceError::from_rpc(ServiceErrorSource::Stash, err))?;
         debug!("Received ZMQ RPC request: {:?}", message);
         Ok(match message {
-            Request::AddGenesis(genesis) => self.rpc_add_genesis(genesis).await,
             Request::AddSchema(schema) => self.rpc_add_schema(schema).await,
+            Request::ListSchemata() => self.rpc_list_schemata().await,
+            Request::ReadSchemata(ids) => self.rpc_read_schemata(ids).await,
+            Request::AddGenesis(genesis) => self.rpc_add_genesis(genesis).await,
+            Request::ListGeneses() => self.rpc_list_geneses().await,
             Request::ReadGenesis(contract_id) => self.rpc_read_genesis(contract_id).await,
+            Request::ReadTransitions(ids) => self.rpc_read_transitions(ids).await,
             Request::Consign(consign) => self.rpc_consign(consign).await,
             Request::Validate(consign) => self.rpc_validate(consign).await,
+            Request::Import(consign) => self.rpc_import(consign).await,
             Request::Merge(merge) => self.rpc_merge(merge).await,
             Request::Forget(removal_list) => self.rpc_forget(removal_list).await,
-            _ => unimplemented!(),
+            Request::Network() => self.rpc_network().await,
         }
         .map_err(|err| ServiceError {
             domain: err,
@@ -177,12 +198,38 @@ impl Runtime {
         Ok(Reply::Success)
     }
 
+    async fn rpc_list_schemata(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LIST_SCHEMATA");
+        let ids = self.storage.schema_ids()?;
+        Ok(Reply::SchemaIds(ids))
+    }
+
+    async fn rpc_read_schemata(
+        &mut self,
+        ids: &Vec<SchemaId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_SCHEMATA {:?}", ids);
+        let schemata = ids
+            .iter()
+            .map(|id| self.storage.schema(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Reply::Schemata(schemata))
+    }
+
     async fn rpc_add_genesis(&mut self, genesis: &Genesis) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got ADD_GENESIS {}", genesis);
+        ServiceErrorDomain::check_network(&self.config.network, &genesis.network())?;
         self.storage.add_genesis(genesis)?;
+        self.notify(Notification::GenesisAdded(genesis.contract_id()));
         Ok(Reply::Success)
     }
 
+    async fn rpc_list_geneses(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got LIST_GENESES");
+        let ids = self.storage.contract_ids()?;
+        Ok(Reply::ContractIds(ids))
+    }
+
     async fn rpc_read_genesis(
         &mut self,
         contract_id: &ContractId,
@@ -192,6 +239,18 @@ impl Runtime {
         Ok(Reply::Genesis(genesis))
     }
 
+    async fn rpc_read_transitions(
+        &mut self,
+        ids: &Vec<NodeId>,
+    ) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got READ_TRANSITIONS {:?}", ids);
+        let transitions = ids
+            .iter()
+            .map(|id| self.storage.transition(id))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Reply::Transitions(transitions))
+    }
+
     async fn rpc_consign(&mut self, request: &ConsignRequest) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got CONSIGN {}", request);
 
@@ -219,6 +278,17 @@ impl Runtime {
             )
             .map_err(|_| ServiceErrorDomain::Stash)?;
 
+        // The new state transition is created locally, so we keep it (with
+        // all its seals revealed) in the stash: it will be required for
+        // consigning the assets it has allocated to us
+        self.merge(Consignment::with(
+            consignment.genesis.clone(),
+            consignment.endpoints.clone(),
+            vec![(anchor, request.transition.clone())],
+        ))
+        .map_err(|_| ServiceErrorDomain::Stash)?;
+        self.notify(Notification::ConsignmentMerged(request.contract_id));
+
         Ok(Reply::Transfer(reply::Transfer { consignment, psbt }))
     }
 
@@ -227,143 +297,127 @@ impl Runtime {
         consignment: &Consignment,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got VALIDATE CONSIGNMENT");
+        ServiceErrorDomain::check_network(&self.config.network, &consignment.genesis.network())?;
+
+        // Validation must not have any side effects: nothing from the
+        // consignment is stored unless it is explicitly imported
+        let validation_status = self.validate(consignment)?;
+
+        Ok(Reply::ValidationStatus(reply::ValidationStatus::with(
+            &validation_status,
+            consignment,
+        )))
+    }
 
-        let schema = self
-            .storage()
-            .schema(&consignment.genesis.schema_id())
-            .map_err(|_| ServiceErrorDomain::Storage)?;
-
-        // [VALIDATION]: Validate genesis node against the scheme
-        let validation_status = consignment.validate(&schema, tx_resolver);
-
-        self.storage.add_genesis(&consignment.genesis)?;
-
-        match validation_status.validity() {
-            Validity::Valid => Ok(Reply::Success),
-            Validity::UnresolvedTransactions => Ok(Reply::Failure(reply::Failure {
-                code: 1,
-                info: format!("{:?}", validation_status.unresolved_txids),
-            })),
-            Validity::Invalid => Ok(Reply::Failure(reply::Failure {
-                code: 2,
-                info: format!("{:?}", validation_status.failures),
-            })),
+    async fn rpc_import(&mut self, consignment: &Consignment) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got IMPORT CONSIGNMENT");
+        ServiceErrorDomain::check_network(&self.config.network, &consignment.genesis.network())?;
+
+        let validation_status = self.validate(consignment)?;
+
+        // Genesis, transitions and anchors are persisted only if the
+        // consignment is completely valid
+        if validation_status.validity() == Validity::Valid {
+            self.merge(consignment.clone())?;
+            self.notify(Notification::ConsignmentMerged(
+                consignment.genesis.contract_id(),
+            ));
+        } else {
+            warn!(
+                "Consignment is not valid ({:?}) and will not be imported",
+                validation_status.validity()
+            );
         }
-        // TODO: Return this type of reply when StrictEncoding will be
-        //       implemented for validation::Status
-        //Ok(Reply::ValidationStatus(validation_status))
+
+        Ok(Reply::ValidationStatus(reply::ValidationStatus::with(
+            &validation_status,
+            consignment,
+        )))
     }
 
     async fn rpc_merge(&mut self, merge: &MergeRequest) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got MERGE CONSIGNMENT");
+        ServiceErrorDomain::check_network(
+            &self.config.network,
+            &merge.consignment.genesis.network(),
+        )?;
 
-        let known_seals: HashMap<OutpointHash, OutpointReveal> = merge
-            .reveal_outpoints
-            .iter()
-            .map(|rev| (rev.conceal(), rev.clone()))
-            .collect();
-
-        // TODO: Move this to processor mod
-        // [PRIVACY]:
-        // Update transition data with the revealed state information
-        // that we kept since we did an invoice (and the sender did not
-        // know)
+        // Update genesis and transition data with the revealed seals that we
+        // kept since we did an invoice (and the sender did not know)
+        let mut genesis = merge.consignment.genesis.clone();
+        let mut revealed = genesis.reveal_seals(&merge.reveal_outpoints);
         let mut data = Vec::<_>::with_capacity(merge.consignment.data.len());
         for (anchor, transition) in &merge.consignment.data {
             let mut transition = transition.clone();
-            transition
-                .assignments_mut()
-                .into_iter()
-                .for_each(|(_, assignment)| match assignment {
-                    AssignmentsVariant::Declarative(_) => {}
-                    AssignmentsVariant::DiscreteFiniteField(set) => {
-                        set.clone().iter().for_each(|a| match a {
-                            Assignment::Confidential {
-                                seal_definition,
-                                assigned_state,
-                            } => {
-                                if let Some(reveal) = known_seals.get(seal_definition) {
-                                    set.remove(a);
-                                    set.insert(Assignment::ConfidentialAmount {
-                                        seal_definition: seal::Revealed::TxOutpoint(reveal.clone()),
-                                        assigned_state: assigned_state.clone(),
-                                    });
-                                };
-                            }
-                            Assignment::ConfidentialSeal {
-                                seal_definition,
-                                assigned_state,
-                            } => {
-                                if let Some(reveal) = known_seals.get(seal_definition) {
-                                    set.remove(a);
-                                    set.insert(Assignment::Revealed {
-                                        seal_definition: seal::Revealed::TxOutpoint(reveal.clone()),
-                                        assigned_state: assigned_state.clone(),
-                                    });
-                                };
-                            }
-                            _ => {}
-                        });
-                    }
-                    AssignmentsVariant::CustomData(set) => {
-                        set.clone().iter().for_each(|a| match a {
-                            Assignment::Confidential {
-                                seal_definition,
-                                assigned_state,
-                            } => {
-                                if let Some(reveal) = known_seals.get(seal_definition) {
-                                    set.remove(a);
-                                    set.insert(Assignment::ConfidentialAmount {
-                                        seal_definition: seal::Revealed::TxOutpoint(reveal.clone()),
-                                        assigned_state: assigned_state.clone(),
-                                    });
-                                };
-                            }
-                            Assignment::ConfidentialSeal {
-                                seal_definition,
-                                assigned_state,
-                            } => {
-                                if let Some(reveal) = known_seals.get(seal_definition) {
-                                    set.remove(a);
-                                    set.insert(Assignment::Revealed {
-                                        seal_definition: seal::Revealed::TxOutpoint(reveal.clone()),
-                                        assigned_state: assigned_state.clone(),
-                                    });
-                                };
-                            }
-                            _ => {}
-                        });
-                    }
-                });
-            data.push((anchor, transition));
+            revealed += transition.reveal_seals(&merge.reveal_outpoints);
+            data.push((anchor.clone(), transition));
         }
-
-        // Store the transition and the anchor data in the stash
-        for (anchor, transition) in data {
-            self.storage.add_anchor(&anchor)?;
-            self.storage.add_transition(&transition)?;
+        debug!("{} seals were revealed in the consignment", revealed);
+
+        // [VALIDATION]: Validate the original consignment before adding
+        // anything to the stash. Unresolved transactions are tolerated only
+        // if explicitly allowed by the configuration.
+        let validation_status = self.validate(&merge.consignment)?;
+        let accepted = match validation_status.validity() {
+            Validity::Valid => true,
+            Validity::UnresolvedTransactions => self.config.allow_unresolved,
+            Validity::Invalid => false,
+        };
+        if !accepted {
+            warn!(
+                "Consignment is not valid ({:?}) and will not be merged",
+                validation_status.validity()
+            );
+            return Ok(Reply::ValidationStatus(reply::ValidationStatus::with(
+                &validation_status,
+                &merge.consignment,
+            )));
         }
 
+        // Store the genesis, transitions and anchor data in the stash and
+        // index the anchors
+        let contract_id = genesis.contract_id();
+        let consignment = Consignment::with(genesis, merge.consignment.endpoints.clone(), data);
+        self.merge(consignment)?;
+        self.notify(Notification::ConsignmentMerged(contract_id));
+
         Ok(Reply::Success)
     }
 
     async fn rpc_forget(
         &mut self,
-        _removal_list: &Vec<(NodeId, u16)>,
+        removal_list: &Vec<(NodeId, u16)>,
     ) -> Result<Reply, ServiceErrorDomain> {
         debug!("Got FORGET");
 
-        // TODO: Implement stash prunning: filter all transitions containing
-        //       revealed outpoints from the removal_list, and if they do
-        //       not have any other _known_ outpoints, remove them — and iterate
-        //       over their direct ancestor in the same manner
+        let forgotten = self.forget(removal_list.clone())?;
+        debug!(
+            "Stash pruned from {} transitions and {} anchors",
+            forgotten.transitions.len(),
+            forgotten.anchors.len()
+        );
+        self.notify(Notification::Forgotten(forgotten.clone()));
 
-        Ok(Reply::Success)
+        Ok(Reply::Forgotten(forgotten))
     }
-}
 
-fn tx_resolver(_txid: &Txid) -> Result<Option<(Transaction, u64)>, validation::TxResolverError> {
-    Err(validation::TxResolverError)
+    async fn rpc_network(&mut self) -> Result<Reply, ServiceErrorDomain> {
+        debug!("Got NETWORK");
+        Ok(Reply::Network(self.config.network.clone()))
+    }
+
+    /// Publishes stash update over the PUB socket. Since the update is
+    /// already persisted, failures are logged and not reported to the client
+    fn notify(&mut self, notification: Notification) {
+        trace!("Publishing ZMQ notification: {:?}", notification);
+        let result = notification
+            .encode()
+            .map_err(RuntimeError::from)
+            .and_then(|data| Ok(self.session_pub.send_raw_message(data)?));
+        if let Err(err) = result {
+            error!("Unable to publish notification {}: {}", notification, err);
+        }
+    }
 }
 
 pub async fn main_with_config(config: Config) -> Result<(), BootstrapError> {
@@ -11,24 +11,50 @@
 // along with this software.
 // If not, see <https://opensource.org/licenses/MIT>.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeSet, HashSet, VecDeque};
 
 use lnpbp::bitcoin::hashes::Hash;
+use lnpbp::bitcoin::{Transaction, Txid};
 use lnpbp::bp::blind::OutpointHash;
-use lnpbp::rgb::{Anchor, AutoConceal, Consignment, ContractId, Node, NodeId, Transition};
+use lnpbp::rgb::{
+    validation, Anchor, AnchorId, Assignment, AssignmentsVariant, AutoConceal, Consignment,
+    ContractId, Node, NodeId, Transition,
+};
 
 use super::index::Index;
 use super::storage::Store;
 use super::Runtime;
+use crate::api::reply;
+use crate::error::ServiceErrorDomain;
 
 #[derive(Clone, PartialEq, Eq, Debug, Display, From, Error)]
 #[display_from(Debug)]
 pub enum Error {
-    #[derive_from(super::storage::DiskStorageError)]
+    #[cfg_attr(
+        not(feature = "store_sled"),
+        derive_from(super::storage::DiskStorageError)
+    )]
+    #[cfg_attr(feature = "store_sled", derive_from(super::storage::SledStorageError))]
     StorageError,
 
     #[derive_from(super::index::BTreeIndexError)]
     IndexError,
+
+    /// Consignment merge has failed and some of the changes it did to the
+    /// stash could not be reverted
+    MergeRevertFailed,
+}
+
+impl From<Error> for ServiceErrorDomain {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::StorageError => ServiceErrorDomain::Storage,
+            Error::IndexError => ServiceErrorDomain::Index,
+            Error::MergeRevertFailed => ServiceErrorDomain::Internal(
+                "Unable to revert changes done by the failed consignment merge".to_string(),
+            ),
+        }
+    }
 }
 
 impl Runtime {
@@ -64,24 +90,222 @@ impl Runtime {
         Ok(Consignment::with(genesis, extended_endpoints, data))
     }
 
+    /// Validates consignment against the schema and bitcoin transaction
+    /// graph. The procedure does not modify stash data.
+    pub fn validate(&self, consignment: &Consignment) -> Result<validation::Status, Error> {
+        let schema = self.storage.schema(&consignment.genesis.schema_id())?;
+
+        Ok(match self.resolver {
+            Some(ref resolver) => consignment.validate(&schema, |txid: &Txid| {
+                resolver.resolve(txid).map_err(|err| {
+                    warn!("Unable to resolve transaction {}: {}", txid, err);
+                    validation::TxResolverError
+                })
+            }),
+            None => consignment.validate(&schema, no_tx_resolver),
+        })
+    }
+
+    /// Stores all consignment data (genesis, state transitions and anchors)
+    /// in the stash and indexes the anchors. The operation is atomic: if any
+    /// of the data can't be stored, all changes done by the procedure are
+    /// reverted; if the revert fails as well, `Error::MergeRevertFailed` is
+    /// returned.
     pub fn merge(&mut self, consignment: Consignment) -> Result<Vec<Box<dyn Node>>, Error> {
+        let mut log = MergeLog::default();
+        self.try_merge(consignment, &mut log).map_err(|err| {
+            error!("Unable to merge consignment, reverting changes: {}", err);
+            if self.revert_merge(log) {
+                err
+            } else {
+                Error::MergeRevertFailed
+            }
+        })
+    }
+
+    fn try_merge(
+        &mut self,
+        consignment: Consignment,
+        log: &mut MergeLog,
+    ) -> Result<Vec<Box<dyn Node>>, Error> {
         let mut nodes: Vec<Box<dyn Node>> = vec![];
-        consignment
-            .data
-            .into_iter()
-            .try_for_each(|(anchor, transition)| -> Result<(), Error> {
-                self.storage
-                    .add_transition(&transition)?
-                    .then(|| nodes.push(Box::new(transition)));
-                self.storage.add_anchor(&anchor)?;
-                self.indexer.index_anchor(&anchor)?;
-                Ok(())
-            })?;
+
         let genesis = consignment.genesis;
-        self.storage
-            .add_genesis(&genesis)?
-            .then(|| nodes.push(Box::new(genesis)));
+        if !self.storage.has_genesis(&genesis.contract_id())? {
+            self.storage.add_genesis(&genesis)?;
+            log.genesis = Some(genesis.contract_id());
+            nodes.push(Box::new(genesis));
+        }
+
+        for (anchor, transition) in consignment.data {
+            let node_id = transition.node_id();
+            let known = self.storage.has_transition(&node_id)?;
+            if known {
+                log.replaced.push(self.storage.transition(&node_id)?);
+            }
+            // We re-write known transitions since the new version may
+            // contain more revealed data
+            self.storage.add_transition(&transition)?;
+            if !known {
+                log.transitions.push(node_id);
+                nodes.push(Box::new(transition));
+            }
+
+            let anchor_id = anchor.anchor_id();
+            if !self.storage.has_anchor(&anchor_id)? {
+                self.storage.add_anchor(&anchor)?;
+                log.anchors.push(anchor.clone());
+            }
+            self.indexer.index_anchor(&anchor)?;
+        }
 
         Ok(nodes)
     }
+
+    /// Reverts changes listed in the merge log; returns `false` if some of
+    /// them could not be reverted
+    fn revert_merge(&mut self, log: MergeLog) -> bool {
+        let mut failed = false;
+        for anchor in log.anchors {
+            failed |= self.indexer.remove_anchor(&anchor).is_err();
+            failed |= self.storage.remove_anchor(&anchor.anchor_id()).is_err();
+        }
+        for node_id in log.transitions {
+            failed |= self.storage.remove_transition(&node_id).is_err();
+        }
+        for transition in log.replaced {
+            failed |= self.storage.add_transition(&transition).is_err();
+        }
+        if let Some(contract_id) = log.genesis {
+            failed |= self.storage.remove_genesis(&contract_id).is_err();
+        }
+        if failed {
+            error!("Unable to revert all changes done by the failed consignment merge");
+        }
+        !failed
+    }
+
+    /// Prunes the stash from the state transitions which do not have any
+    /// known unspent seals left. The removal list contains pairs of transition
+    /// ids and indexes of their assignments which were spent; transitions
+    /// having all of their revealed seals spent are removed and the same
+    /// procedure is repeated for their ancestors. Anchors which are left
+    /// without any known transition are removed as well.
+    ///
+    /// NB: Since the removal list does not specify assignment type, the index
+    /// is treated as spent for assignments of all types within the transition
+    pub fn forget(&mut self, removal_list: Vec<(NodeId, u16)>) -> Result<reply::Forgotten, Error> {
+        let mut spent = removal_list
+            .iter()
+            .map(|(node_id, index)| (*node_id, None, *index))
+            .collect::<HashSet<(NodeId, Option<usize>, u16)>>();
+        let mut sources = removal_list
+            .into_iter()
+            .map(|(node_id, _)| node_id)
+            .collect::<VecDeque<_>>();
+
+        let mut transitions = vec![];
+        let mut anchor_ids = BTreeSet::<AnchorId>::new();
+        while let Some(tsid) = sources.pop_front() {
+            // Genesis is never removed; transitions that are unknown or were
+            // already removed are skipped
+            if transitions.contains(&tsid) || !self.storage.has_transition(&tsid)? {
+                continue;
+            }
+            let transition = self.storage.transition(&tsid)?;
+
+            // Confidential seals can't be tracked by the stash, so only the
+            // revealed ones are checked for being spent
+            let has_unspent = transition
+                .assignments()
+                .iter()
+                .any(|(assignment_type, variant)| {
+                    known_seal_indexes(variant).into_iter().any(|index| {
+                        !spent.contains(&(tsid, None, index))
+                            && !spent.contains(&(tsid, Some(*assignment_type), index))
+                    })
+                });
+            if has_unspent {
+                continue;
+            }
+
+            if let Ok(anchor_id) = self.indexer.anchor_id_by_transition_id(tsid) {
+                anchor_ids.insert(anchor_id);
+            }
+            self.storage.remove_transition(&tsid)?;
+            transitions.push(tsid);
+
+            // All seals closed by the removed transition are spent, so we can
+            // try to prune its ancestors as well
+            for (node_id, assignments) in transition.ancestors() {
+                for (assignment_type, indexes) in assignments {
+                    indexes.iter().for_each(|index| {
+                        spent.insert((*node_id, Some(*assignment_type), *index));
+                    });
+                }
+                sources.push_back(*node_id);
+            }
+        }
+
+        let mut anchors = vec![];
+        for anchor_id in anchor_ids {
+            let anchor = self.storage.anchor(&anchor_id)?;
+            let orphaned = anchor.commitment.commitments.iter().try_fold(
+                true,
+                |orphaned, item| -> Result<bool, Error> {
+                    let tsid = NodeId::from_inner(item.commitment.into_inner());
+                    Ok(orphaned && !self.storage.has_transition(&tsid)?)
+                },
+            )?;
+            if orphaned {
+                self.storage.remove_anchor(&anchor_id)?;
+                self.indexer.remove_anchor(&anchor)?;
+                anchors.push(anchor_id);
+            }
+        }
+
+        Ok(reply::Forgotten {
+            transitions,
+            anchors,
+        })
+    }
+}
+
+/// Changes done to the stash during consignment merge procedure, used to revert
+/// them in case of failure
+#[derive(Clone, Debug, Default)]
+struct MergeLog {
+    genesis: Option<ContractId>,
+    transitions: Vec<NodeId>,
+    replaced: Vec<Transition>,
+    anchors: Vec<Anchor>,
+}
+
+/// Transaction resolver used when no resolver is configured: reports all
+/// transactions as unresolved
+fn no_tx_resolver(_txid: &Txid) -> Result<Option<(Transaction, u64)>, validation::TxResolverError> {
+    Err(validation::TxResolverError)
+}
+
+/// Returns indexes of the assignments within a given variant which have
+/// revealed (i.e. known to us) seal definitions
+fn known_seal_indexes(variant: &AssignmentsVariant) -> Vec<u16> {
+    macro_rules! filter_known {
+        ($set:ident) => {
+            $set.iter()
+                .enumerate()
+                .filter_map(|(index, assignment)| match assignment {
+                    Assignment::Revealed { .. } | Assignment::ConfidentialAmount { .. } => {
+                        Some(index as u16)
+                    }
+                    _ => None,
+                })
+                .collect()
+        };
+    }
+    match variant {
+        AssignmentsVariant::Declarative(set) => filter_known!(set),
+        AssignmentsVariant::DiscreteFiniteField(set) => filter_known!(set),
+        AssignmentsVariant::CustomData(set) => filter_known!(set),
+    }
 }
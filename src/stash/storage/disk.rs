@@ -15,7 +15,7 @@ use std::path::PathBuf;
 use std::{fs, io};
 
 use lnpbp::bitcoin;
-use lnpbp::bitcoin::hashes::hex::{FromHex, ToHex};
+use lnpbp::bitcoin::hashes::hex::ToHex;
 use lnpbp::rgb::prelude::*;
 
 use super::Store;
@@ -190,11 +190,13 @@ impl Store for DiskStorage {
     type Error = DiskStorageError;
 
     fn schema_ids(&self) -> Result<Vec<SchemaId>, Self::Error> {
+        // File names are bech32-encoded ids with extension, so it's more
+        // reliable to read the ids from the data itself
         self.config
             .schema_names()?
             .into_iter()
             .try_fold(vec![], |mut list, name| {
-                list.push(SchemaId::from_hex(&name)?);
+                list.push(Schema::read_file(self.config.schemata_dir().join(name))?.schema_id());
                 Ok(list)
             })
     }
@@ -228,7 +230,7 @@ impl Store for DiskStorage {
             .genesis_names()?
             .into_iter()
             .try_fold(vec![], |mut list, name| {
-                list.push(ContractId::from_hex(&name)?);
+                list.push(Genesis::read_file(self.config.geneses_dir().join(name))?.contract_id());
                 Ok(list)
             })
     }
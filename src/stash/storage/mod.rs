@@ -13,15 +13,15 @@
 
 mod store;
 
-#[cfg(not(store_hammersbald))] // Default store
+#[cfg(not(feature = "store_sled"))] // Default store
 mod disk;
-#[cfg(all(store_hammersbald, not(any(store_disk))))]
-mod hammersbald;
+#[cfg(feature = "store_sled")]
+mod sled;
 
 pub(super) use store::Store;
 
-#[cfg(not(store_hammersbald))] // Default store
+#[cfg(not(feature = "store_sled"))] // Default store
 pub(super) use disk::{DiskStorage, DiskStorageConfig, DiskStorageError};
 
-#[cfg(all(store_hammersbald, not(any(store_disk))))]
-pub(super) use hammersbald::HammersbaldStorage;
+#[cfg(feature = "store_sled")]
+pub(super) use self::sled::{SledStorage, SledStorageConfig, SledStorageError};
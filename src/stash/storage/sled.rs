@@ -0,0 +1,324 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use std::path::PathBuf;
+
+use lnpbp::bitcoin;
+use lnpbp::bitcoin::hashes::Hash;
+use lnpbp::rgb::prelude::*;
+use lnpbp::strict_encoding::{strict_encode, StrictDecode};
+
+use super::Store;
+use crate::error::{BootstrapError, ServiceErrorDomain};
+
+#[derive(Debug, Display, Error, From)]
+#[display_from(Debug)]
+pub enum SledStorageError {
+    #[derive_from]
+    Sled(::sled::Error),
+
+    #[derive_from(bitcoin::hashes::Error)]
+    HashName,
+
+    #[derive_from]
+    Encoding(lnpbp::strict_encoding::Error),
+
+    NotFound,
+}
+
+impl From<SledStorageError> for ServiceErrorDomain {
+    fn from(_: SledStorageError) -> Self {
+        ServiceErrorDomain::Storage
+    }
+}
+
+impl From<SledStorageError> for BootstrapError {
+    fn from(_: SledStorageError) -> Self {
+        BootstrapError::StorageError
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Display)]
+#[display_from(Debug)]
+pub struct SledStorageConfig {
+    pub data_dir: PathBuf,
+}
+
+impl SledStorageConfig {
+    pub const SCHEMATA_TREE: &'static str = "schemata";
+    pub const GENESES_TREE: &'static str = "geneses";
+    pub const ANCHORS_TREE: &'static str = "anchors";
+    pub const TRANSITIONS_TREE: &'static str = "transitions";
+}
+
+/// Keeps all source/binary RGB contract data in embedded key-value database
+/// (sled), with a separate tree for each of the data types. Keys are binary
+/// object ids and values are strict-encoded objects.
+#[derive(Debug, Display)]
+#[display_from(Debug)]
+pub struct SledStorage {
+    db: ::sled::Db,
+    schemata: ::sled::Tree,
+    geneses: ::sled::Tree,
+    anchors: ::sled::Tree,
+    transitions: ::sled::Tree,
+}
+
+impl SledStorage {
+    pub fn new(config: SledStorageConfig) -> Result<Self, SledStorageError> {
+        debug!(
+            "Instantiating RGB storage (sled database at {:?}) ...",
+            config.data_dir
+        );
+
+        let db = ::sled::open(&config.data_dir)?;
+        Ok(Self {
+            schemata: db.open_tree(SledStorageConfig::SCHEMATA_TREE)?,
+            geneses: db.open_tree(SledStorageConfig::GENESES_TREE)?,
+            anchors: db.open_tree(SledStorageConfig::ANCHORS_TREE)?,
+            transitions: db.open_tree(SledStorageConfig::TRANSITIONS_TREE)?,
+            db,
+        })
+    }
+
+    fn ids<T>(tree: &::sled::Tree) -> Result<Vec<T>, SledStorageError>
+    where
+        T: Hash,
+    {
+        tree.iter().keys().try_fold(vec![], |mut list, key| {
+            list.push(T::from_slice(&key?[..])?);
+            Ok(list)
+        })
+    }
+
+    fn get<T>(tree: &::sled::Tree, key: &[u8]) -> Result<T, SledStorageError>
+    where
+        T: StrictDecode<Error = lnpbp::strict_encoding::Error>,
+    {
+        let data = tree.get(key)?.ok_or(SledStorageError::NotFound)?;
+        Ok(T::strict_decode(&data[..])?)
+    }
+
+    fn insert(
+        &self,
+        tree: &::sled::Tree,
+        key: &[u8],
+        data: Vec<u8>,
+    ) -> Result<bool, SledStorageError> {
+        let exists = tree.insert(key, data)?.is_some();
+        self.db.flush()?;
+        Ok(exists)
+    }
+
+    fn remove(&self, tree: &::sled::Tree, key: &[u8]) -> Result<bool, SledStorageError> {
+        let existed = tree.remove(key)?.is_some();
+        self.db.flush()?;
+        Ok(existed)
+    }
+}
+
+impl Store for SledStorage {
+    type Error = SledStorageError;
+
+    #[inline]
+    fn schema_ids(&self) -> Result<Vec<SchemaId>, Self::Error> {
+        Self::ids(&self.schemata)
+    }
+
+    #[inline]
+    fn schema(&self, id: &SchemaId) -> Result<Schema, Self::Error> {
+        Self::get(&self.schemata, &id[..])
+    }
+
+    #[inline]
+    fn has_schema(&self, id: &SchemaId) -> Result<bool, Self::Error> {
+        Ok(self.schemata.contains_key(&id[..])?)
+    }
+
+    fn add_schema(&self, schema: &Schema) -> Result<bool, Self::Error> {
+        self.insert(
+            &self.schemata,
+            &schema.schema_id()[..],
+            strict_encode(schema)?,
+        )
+    }
+
+    fn remove_schema(&self, id: &SchemaId) -> Result<bool, Self::Error> {
+        self.remove(&self.schemata, &id[..])
+    }
+
+    #[inline]
+    fn contract_ids(&self) -> Result<Vec<ContractId>, Self::Error> {
+        Self::ids(&self.geneses)
+    }
+
+    #[inline]
+    fn genesis(&self, id: &ContractId) -> Result<Genesis, Self::Error> {
+        Self::get(&self.geneses, &id[..])
+    }
+
+    #[inline]
+    fn has_genesis(&self, id: &ContractId) -> Result<bool, Self::Error> {
+        Ok(self.geneses.contains_key(&id[..])?)
+    }
+
+    fn add_genesis(&self, genesis: &Genesis) -> Result<bool, Self::Error> {
+        self.insert(
+            &self.geneses,
+            &genesis.contract_id()[..],
+            strict_encode(genesis)?,
+        )
+    }
+
+    fn remove_genesis(&self, id: &ContractId) -> Result<bool, Self::Error> {
+        self.remove(&self.geneses, &id[..])
+    }
+
+    #[inline]
+    fn anchor(&self, id: &AnchorId) -> Result<Anchor, Self::Error> {
+        Self::get(&self.anchors, &id[..])
+    }
+
+    #[inline]
+    fn has_anchor(&self, id: &AnchorId) -> Result<bool, Self::Error> {
+        Ok(self.anchors.contains_key(&id[..])?)
+    }
+
+    fn add_anchor(&self, anchor: &Anchor) -> Result<bool, Self::Error> {
+        self.insert(
+            &self.anchors,
+            &anchor.anchor_id()[..],
+            strict_encode(anchor)?,
+        )
+    }
+
+    fn remove_anchor(&self, id: &AnchorId) -> Result<bool, Self::Error> {
+        self.remove(&self.anchors, &id[..])
+    }
+
+    #[inline]
+    fn transition(&self, id: &NodeId) -> Result<Transition, Self::Error> {
+        Self::get(&self.transitions, &id[..])
+    }
+
+    #[inline]
+    fn has_transition(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        Ok(self.transitions.contains_key(&id[..])?)
+    }
+
+    fn add_transition(&self, transition: &Transition) -> Result<bool, Self::Error> {
+        self.insert(
+            &self.transitions,
+            &transition.node_id()[..],
+            strict_encode(transition)?,
+        )
+    }
+
+    fn remove_transition(&self, id: &NodeId) -> Result<bool, Self::Error> {
+        self.remove(&self.transitions, &id[..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fungible::schema;
+    use crate::util::file::ReadWrite;
+
+    fn storage(name: &str) -> (SledStorage, PathBuf) {
+        let data_dir =
+            std::env::temp_dir().join(format!("rgb-sled-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&data_dir);
+        let storage = SledStorage::new(SledStorageConfig {
+            data_dir: data_dir.clone(),
+        })
+        .unwrap();
+        (storage, data_dir)
+    }
+
+    fn consignment() -> Consignment {
+        Consignment::read_file(PathBuf::from("test/consignment.rgb"))
+            .expect("Test consignment must be readable")
+    }
+
+    #[test]
+    fn schema_round_trip() {
+        let (storage, data_dir) = storage("schema");
+        let schema = schema::schema();
+        let id = schema.schema_id();
+
+        assert!(!storage.has_schema(&id).unwrap());
+        assert_eq!(storage.add_schema(&schema).unwrap(), false);
+        assert_eq!(storage.add_schema(&schema).unwrap(), true);
+        assert!(storage.has_schema(&id).unwrap());
+        assert_eq!(storage.schema(&id).unwrap().schema_id(), id);
+        assert_eq!(storage.schema_ids().unwrap(), vec![id]);
+
+        assert_eq!(storage.remove_schema(&id).unwrap(), true);
+        assert_eq!(storage.remove_schema(&id).unwrap(), false);
+        assert!(!storage.has_schema(&id).unwrap());
+        assert!(storage.schema(&id).is_err());
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn genesis_round_trip() {
+        let (storage, data_dir) = storage("genesis");
+        let genesis = consignment().genesis;
+        let id = genesis.contract_id();
+
+        assert!(!storage.has_genesis(&id).unwrap());
+        assert_eq!(storage.add_genesis(&genesis).unwrap(), false);
+        assert!(storage.has_genesis(&id).unwrap());
+        assert_eq!(storage.genesis(&id).unwrap().contract_id(), id);
+        assert_eq!(storage.contract_ids().unwrap(), vec![id]);
+
+        assert_eq!(storage.remove_genesis(&id).unwrap(), true);
+        assert!(!storage.has_genesis(&id).unwrap());
+        assert!(storage.contract_ids().unwrap().is_empty());
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+
+    #[test]
+    fn transition_and_anchor_round_trip() {
+        let (storage, data_dir) = storage("transition");
+        let (anchor, transition) = consignment()
+            .data
+            .into_iter()
+            .next()
+            .expect("Test consignment must contain state transitions");
+        let node_id = transition.node_id();
+        let anchor_id = anchor.anchor_id();
+
+        assert!(!storage.has_transition(&node_id).unwrap());
+        assert_eq!(storage.add_transition(&transition).unwrap(), false);
+        assert!(storage.has_transition(&node_id).unwrap());
+        assert_eq!(storage.transition(&node_id).unwrap().node_id(), node_id);
+
+        assert!(!storage.has_anchor(&anchor_id).unwrap());
+        assert_eq!(storage.add_anchor(&anchor).unwrap(), false);
+        assert!(storage.has_anchor(&anchor_id).unwrap());
+        assert_eq!(storage.anchor(&anchor_id).unwrap().anchor_id(), anchor_id);
+
+        assert_eq!(storage.remove_transition(&node_id).unwrap(), true);
+        assert!(!storage.has_transition(&node_id).unwrap());
+        assert!(storage.transition(&node_id).is_err());
+        assert_eq!(storage.remove_anchor(&anchor_id).unwrap(), true);
+        assert!(!storage.has_anchor(&anchor_id).unwrap());
+        assert!(storage.anchor(&anchor_id).is_err());
+
+        let _ = std::fs::remove_dir_all(data_dir);
+    }
+}
@@ -1,6 +1,6 @@
 use core::convert::TryFrom;
 use std::io::{Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use lnpbp::rgb::prelude::*;
@@ -25,6 +25,29 @@ pub fn file(filename: PathBuf, mode: FileMode) -> Result<fs::File, io::Error> {
         .open(filename)
 }
 
+#[inline]
+pub fn tmp_filename(filename: &Path) -> PathBuf {
+    let mut name = filename.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// Writes data to a temporary file, syncs it to the disk and renames it into
+/// the target file, so the target is always either in its old or new state
+pub fn write_atomically(filename: &Path, data: &[u8]) -> Result<(), io::Error> {
+    let tmp = tmp_filename(filename);
+    let mut f = file(tmp.clone(), FileMode::Create)?;
+    f.set_len(0)?;
+    f.write_all(data)?;
+    f.sync_all()?;
+    fs::rename(&tmp, filename)?;
+    if let Some(dir) = filename.parent() {
+        // Persist the rename itself; not supported on all platforms
+        let _ = fs::File::open(dir).and_then(|dir| dir.sync_all());
+    }
+    Ok(())
+}
+
 pub fn read_file(filename: PathBuf) -> Result<(u32, Vec<u8>), io::Error> {
     let mut data = vec![];
     let mut file = file(filename, FileMode::Read)?;
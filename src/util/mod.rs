@@ -15,7 +15,9 @@
 mod macros;
 pub mod file;
 mod magic_numbers;
+mod reveals;
 mod seal_spec;
 
 pub use magic_numbers::MagicNumber;
+pub use reveals::{RevealSpec, RevealStore, RevealStoreError};
 pub use seal_spec::SealSpec;
@@ -0,0 +1,167 @@
+// RGB standard library
+// Written in 2020 by
+//     Dr. Maxim Orlovsky <orlovsky@pandoracore.com>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the MIT License
+// along with this software.
+// If not, see <https://opensource.org/licenses/MIT>.
+
+use core::fmt;
+use core::str::FromStr;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{fs, io};
+
+use lnpbp::bitcoin::{OutPoint, Txid};
+use lnpbp::bp::blind::{OutpointHash, OutpointReveal};
+use lnpbp::client_side_validation::Conceal;
+
+use crate::error::{ParseError, ServiceErrorDomain};
+use crate::util::file::write_atomically;
+
+#[derive(Debug, Display, Error, From)]
+#[display_from(Debug)]
+pub enum RevealStoreError {
+    #[derive_from]
+    Io(io::Error),
+
+    #[derive_from]
+    Yaml(serde_yaml::Error),
+}
+
+impl From<RevealStoreError> for ServiceErrorDomain {
+    fn from(err: RevealStoreError) -> Self {
+        ServiceErrorDomain::Internal(format!("{}", err))
+    }
+}
+
+/// Outpoint together with its blinding factor, in form of
+/// `<blinding>@<txid>:<vout>`
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct RevealSpec {
+    pub txid: Txid,
+    pub vout: u16,
+    pub blinding: u64,
+}
+
+impl From<RevealSpec> for OutpointReveal {
+    fn from(spec: RevealSpec) -> Self {
+        OutpointReveal {
+            blinding: spec.blinding,
+            txid: spec.txid,
+            vout: spec.vout,
+        }
+    }
+}
+
+impl From<&OutpointReveal> for RevealSpec {
+    fn from(reveal: &OutpointReveal) -> Self {
+        RevealSpec {
+            txid: reveal.txid,
+            vout: reveal.vout,
+            blinding: reveal.blinding,
+        }
+    }
+}
+
+impl fmt::Display for RevealSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}:{}", self.blinding, self.txid, self.vout)
+    }
+}
+
+impl FromStr for RevealSpec {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '@');
+        match (parts.next(), parts.next()) {
+            (Some(blinding), Some(outpoint)) => {
+                let outpoint = OutPoint::from_str(outpoint).map_err(|_| ParseError)?;
+                if outpoint.vout > core::u16::MAX as u32 {
+                    Err(ParseError)?
+                }
+                Ok(Self {
+                    txid: outpoint.txid,
+                    vout: outpoint.vout as u16,
+                    blinding: blinding.parse()?,
+                })
+            }
+            _ => Err(ParseError),
+        }
+    }
+}
+
+/// Storage for the blinding data of locally-controlled outpoints (used in
+/// invoices and as transfer change outputs), which is required to accept the
+/// assets allocated to the blinded seals. Data are indexed by the concealed
+/// outpoint hash and kept in a YAML file, which is re-written on each update.
+pub struct RevealStore {
+    filename: PathBuf,
+    reveals: BTreeMap<OutpointHash, OutpointReveal>,
+}
+
+impl RevealStore {
+    pub const FILENAME: &'static str = "outpoint_reveals.yaml";
+
+    /// Opens the store in the given data directory; if the store file does
+    /// not exist yet, it will be created on the first update
+    pub fn open(data_dir: PathBuf) -> Result<Self, RevealStoreError> {
+        let filename = data_dir.join(Self::FILENAME);
+        let records: Vec<RevealSpec> = if filename.exists() {
+            serde_yaml::from_reader(fs::File::open(&filename)?)?
+        } else {
+            vec![]
+        };
+        let reveals = records
+            .into_iter()
+            .map(OutpointReveal::from)
+            .map(|reveal| (reveal.conceal(), reveal))
+            .collect();
+        Ok(Self { filename, reveals })
+    }
+
+    /// Generates a new random blinding factor for the outpoint, stores the
+    /// reveal data and returns them
+    pub fn blind(&mut self, outpoint: OutPoint) -> Result<OutpointReveal, RevealStoreError> {
+        let reveal = OutpointReveal::from(outpoint);
+        self.add(&reveal)?;
+        Ok(reveal)
+    }
+
+    pub fn add(&mut self, reveal: &OutpointReveal) -> Result<bool, RevealStoreError> {
+        let outpoint_hash = reveal.conceal();
+        if self.reveals.contains_key(&outpoint_hash) {
+            return Ok(false);
+        }
+        self.reveals.insert(outpoint_hash, reveal.clone());
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Finds reveal data for the blinded outpoint
+    pub fn reveal(&self, outpoint_hash: &OutpointHash) -> Option<OutpointReveal> {
+        self.reveals.get(outpoint_hash).cloned()
+    }
+
+    fn save(&self) -> Result<(), RevealStoreError> {
+        if let Some(dir) = self.filename.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let records = self
+            .reveals
+            .values()
+            .map(RevealSpec::from)
+            .collect::<Vec<_>>();
+        // Reveal data can't be restored once lost, so the file is never left
+        // in a partially written state
+        write_atomically(&self.filename, &serde_yaml::to_vec(&records)?)?;
+        Ok(())
+    }
+}